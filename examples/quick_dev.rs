@@ -98,6 +98,12 @@ async fn main() -> Result<()> {
         .print()
         .await?;
 
+    // 获取指定 Pod 指定容器的最后 100 行日志（follow=true 时以流的方式返回）
+    hc.do_get("/api/v1/namespaces/beta-popcloud/pods/popmart-nginx-7bb6cc5487-qz87f/logs?container=nginx&tailLines=100&timestamps=true")
+        .await?
+        .print()
+        .await?;
+
     // 在指定命名空间下的指定 Pod 中执行命令
     let pod_exec_params: serde_json::Value = json!({
         "container":Some("alpine"),
@@ -150,10 +156,40 @@ async fn main() -> Result<()> {
     .print()
     .await?;
 
+    // 重启一个 Deployment
+    hc.do_post(
+        "/api/v1/namespaces/beta-popcloud/deployments/alpine/restart",
+        json!({}),
+    )
+    .await?
+    .print()
+    .await?;
+
+    // 扩缩容一个 Deployment
+    hc.do_post(
+        "/api/v1/namespaces/beta-popcloud/deployments/alpine/scale",
+        json!({ "replicas": 2 }),
+    )
+    .await?
+    .print()
+    .await?;
+
     // 获取指定命名空间下的指定 Deployment
     hc.do_get("/api/v1/namespaces/beta-popcloud/deployments/alpine")
         .await?
         .print()
         .await?;
+
+    // 等待指定 Deployment 发布完成（最多 120 秒）
+    hc.do_get("/api/v1/namespaces/beta-popcloud/deployments/alpine/status?wait=true&timeoutSeconds=120")
+        .await?
+        .print()
+        .await?;
+
+    // 通过通用资源接口获取指定命名空间下的 CronJob
+    hc.do_get("/apis/batch/v1/namespaces/beta-popcloud/cronjobs")
+        .await?
+        .print()
+        .await?;
     Ok(())
 }
\ No newline at end of file
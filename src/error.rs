@@ -1,4 +1,5 @@
 pub type Result<T> = core::result::Result<T, Error>;
+use crate::models::template::FieldError;
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
@@ -9,9 +10,11 @@ use std::fmt;
 pub enum Error {
     General(anyhow::Error),
     TemplateNotFound,
-    UnsupportedKind,
-    ResourceNotFound,
+    UnsupportedKind(String),
+    ResourceNotFound(String),
     ResourceAlreadyExists(anyhow::Error),
+    BadRequest(String),
+    InvalidValues(Vec<FieldError>),
 }
 
 impl fmt::Display for Error {
@@ -20,19 +23,31 @@ impl fmt::Display for Error {
             Self::General(e) => write!(f, "{}", e),
             Self::ResourceAlreadyExists(e) => write!(f, "{}", e),
             Self::TemplateNotFound => write!(f, "Template not found"),
-            Self::UnsupportedKind => write!(f, "UnsupportedKind"),
-            _ => write!(f, "InternalServerError"),
+            Self::UnsupportedKind(kind) => write!(f, "Unsupported kind: {}", kind),
+            Self::BadRequest(msg) => write!(f, "{}", msg),
+            Self::ResourceNotFound(msg) => write!(f, "{}", msg),
+            Self::InvalidValues(errors) => write!(f, "{} invalid template values", errors.len()),
         }
     }
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
-        let body = serde_json::json!({
+        let mut body = serde_json::json!({
             "error": self.to_string()
-        })
-        .to_string();
-        (StatusCode::INTERNAL_SERVER_ERROR, body).into_response()
+        });
+        if let Self::InvalidValues(errors) = &self {
+            body["fields"] = serde_json::json!(errors);
+        }
+        let body = body.to_string();
+        let status = match self {
+            Self::BadRequest(_) | Self::UnsupportedKind(_) | Self::InvalidValues(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            Self::ResourceNotFound(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, body).into_response()
     }
 }
 
@@ -17,18 +17,23 @@ async fn main() -> Result<()> {
     let client = Arc::new(Client::try_default().await.unwrap());
 
     info!("starting up");
+    services::template_service::watch_sources(client.clone()).await;
 
     let k8s_api = Router::new()
         .merge(web::namespaces::routes(client.clone()))
         .merge(web::pods::routes(client.clone()))
         .merge(web::deployments::routes(client.clone()))
         .merge(web::configmaps::routes(client.clone()))
-        .merge(web::ingress::routes(client.clone()));
+        .merge(web::ingress::routes(client.clone()))
+        .merge(web::events::routes(client.clone()))
+        .merge(web::releases::routes(client.clone()))
+        .merge(web::catalog::routes(client.clone()));
 
     let app = Router::new();
     let routes_all = Router::new()
         .merge(routes_ping())
         .nest("/api/v1", k8s_api)
+        .nest("/apis", web::resources::routes(client.clone()))
         .merge(app)
         .layer(middleware::from_fn_with_state(
             client.clone(),
@@ -0,0 +1,133 @@
+use crate::models::event::EventSummary;
+use serde::{Deserialize, Serialize};
+
+/// Where a rollout stands, as reported by `kubectl rollout status`
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RolloutState {
+    Progressing,
+    Complete,
+    Failed,
+    TimedOut,
+}
+
+/// Structured rollout status of a workload
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RolloutStatus {
+    pub name: String,
+    pub state: RolloutState,
+    /// Human readable summary, e.g. `2 of 3 updated replicas are available`
+    pub message: String,
+    pub generation: Option<i64>,
+    pub observed_generation: Option<i64>,
+    /// Desired number of replicas
+    pub replicas: i32,
+    pub updated_replicas: i32,
+    pub ready_replicas: i32,
+    pub available_replicas: i32,
+    pub unavailable_replicas: i32,
+    pub paused: bool,
+    pub conditions: Vec<ConditionSummary>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionSummary {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub status: String,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+    pub last_update_time: Option<String>,
+}
+
+/// A deployment's rollout status together with its recent events
+#[derive(Serialize)]
+pub struct DeploymentDetail {
+    #[serde(flatten)]
+    pub rollout: RolloutStatus,
+    pub events: Vec<EventSummary>,
+}
+
+/// Query parameters for reading the rollout status
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RolloutStatusParams {
+    /// Block until the rollout completes, fails or times out
+    pub wait: Option<bool>,
+    /// Stream every status change as Server-Sent Events while waiting
+    pub sse: Option<bool>,
+    /// Give up waiting after this many seconds, defaults to 600
+    pub timeout_seconds: Option<u64>,
+}
+
+/// A revision of a deployment, backed by one of its ReplicaSets
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Revision {
+    pub revision: i64,
+    pub replica_set: String,
+    pub images: Vec<String>,
+    pub change_cause: Option<String>,
+    pub created_at: Option<String>,
+    pub replicas: i32,
+    /// The revision the deployment is currently running
+    pub current: bool,
+}
+
+/// Parameters for rolling a deployment back
+#[derive(Deserialize, Default)]
+pub struct RollbackParams {
+    /// Revision to restore, defaults to the one before the current revision
+    pub revision: Option<i64>,
+}
+
+/// Parameters for scaling a workload
+#[derive(Deserialize)]
+pub struct ScaleParams {
+    pub replicas: i32,
+}
+
+/// Parameters for changing the images of a workload's containers
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetImageParams {
+    pub containers: Vec<ContainerImage>,
+    /// Recorded as the `kubernetes.io/change-cause` annotation, generated if absent
+    pub change_cause: Option<String>,
+    /// Wait for the rollout to settle before responding
+    pub wait: Option<bool>,
+    /// Give up waiting after this many seconds, defaults to 600
+    pub timeout_seconds: Option<u64>,
+}
+
+/// The new image of a container or init container, either a full image
+/// reference or only a tag to put on the current image
+#[derive(Deserialize)]
+pub struct ContainerImage {
+    pub name: String,
+    pub image: Option<String>,
+    pub tag: Option<String>,
+}
+
+/// Outcome of changing the images of a workload
+#[derive(Serialize)]
+pub struct SetImageResult {
+    /// The changed containers as `name=image`
+    pub updated: Vec<String>,
+    /// Rollout status after the change, absent for CronJobs
+    pub rollout: Option<RolloutStatus>,
+}
+
+/// Query parameters selecting how a patch is applied
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchQuery {
+    /// `merge`, `strategic`, `json` or `apply`, taken from the content type if absent
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    /// Field manager recorded for the change, defaults to `kube-sphere`
+    pub field_manager: Option<String>,
+    /// Take over conflicting fields from other managers on server-side apply
+    pub force: Option<bool>,
+}
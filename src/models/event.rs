@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// Query parameters for filtering events
+#[derive(Deserialize, Default)]
+pub struct EventQuery {
+    /// Kind of the involved object, e.g. `Pod` or `Deployment`
+    pub kind: Option<String>,
+    /// Name of the involved object
+    pub name: Option<String>,
+    /// `Normal` or `Warning`
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    /// Return at most this many of the most recent events
+    pub limit: Option<usize>,
+}
+
+/// An event, with repeated occurrences folded together
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EventSummary {
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+    /// The involved object as `Kind/name`
+    pub object: String,
+    pub count: i32,
+    pub source: Option<String>,
+    pub first_seen: Option<String>,
+    pub last_seen: Option<String>,
+    /// Time since the last occurrence, e.g. `3m`
+    pub age: Option<String>,
+}
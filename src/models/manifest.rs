@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+
+/// Query parameters for submitting a manifest bundle or template
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestParams {
+    /// Server-side apply every document instead of creating it, so the
+    /// same bundle can be submitted again to update it
+    pub apply: Option<bool>,
+    /// Take over conflicting fields from other managers when applying
+    pub force: Option<bool>,
+    /// Only run the submission as a server-side dry run and report a diff
+    /// against the live objects
+    pub dry_run: Option<bool>,
+    /// Roll back every change of the bundle if any document fails
+    pub atomic: Option<bool>,
+}
+
+/// What applying a document did to its live object
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApplyAction {
+    Created,
+    Configured,
+    Unchanged,
+}
+
+/// Outcome of applying or previewing one document of a bundle
+#[derive(Serialize)]
+pub struct ApplyResult {
+    pub kind: String,
+    pub name: String,
+    /// Absent if the document failed
+    pub action: Option<ApplyAction>,
+    /// Unified diff from the live object to the dry-run result
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+    pub error: Option<String>,
+}
+
+/// What rolling back a document did to its object
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum RollbackAction {
+    /// The document had created the object
+    Deleted,
+    /// The document had updated the object, its previous state was put back
+    Restored,
+}
+
+/// Outcome of rolling back one document of a failed bundle
+#[derive(Serialize)]
+pub struct RollbackResult {
+    pub kind: String,
+    pub name: String,
+    /// Absent if the rollback failed, leaving the object as applied
+    pub action: Option<RollbackAction>,
+    pub error: Option<String>,
+}
+
+/// Report of an all-or-nothing submission
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AtomicReport {
+    /// Whether every document was applied and nothing was rolled back
+    pub succeeded: bool,
+    pub results: Vec<ApplyResult>,
+    /// Undone changes, most recent first
+    pub rolled_back: Vec<RollbackResult>,
+}
+
+/// Query parameters for deleting the objects of a bundle or template
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteManifestParams {
+    /// `Foreground`, `Background` or `Orphan`, the default of each kind if absent
+    pub propagation_policy: Option<String>,
+}
+
+/// What deleting a document did to its live object
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum DeleteAction {
+    Deleted,
+    /// There was no live object to delete
+    NotFound,
+}
+
+/// Outcome of deleting one document of a bundle
+#[derive(Serialize)]
+pub struct DeleteResult {
+    pub kind: String,
+    pub name: String,
+    /// Absent if the delete failed
+    pub action: Option<DeleteAction>,
+    pub error: Option<String>,
+}
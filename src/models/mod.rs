@@ -1,2 +1,8 @@
 pub mod pod;
-pub mod namespace;
\ No newline at end of file
+pub mod namespace;
+pub mod event;
+pub mod deployment;
+pub mod resource;
+pub mod manifest;
+pub mod release;
+pub mod template;
\ No newline at end of file
@@ -1,10 +1,239 @@
-use serde::Deserialize;
+use crate::models::event::EventSummary;
+use k8s_openapi::api::core::v1::Pod;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
 
 /// Parameters for executing a command in a specified container
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ExecParams {
     /// The target container's name, if specific container is required
     pub container: Option<String>,
     /// The commands to be executed
     pub commands: Vec<String>,
+    /// Give up on the command after this many seconds, defaults to 60
+    pub timeout_seconds: Option<u64>,
+    /// Keep at most this many bytes of stdout and of stderr, defaults to 1 MiB
+    pub max_output_bytes: Option<usize>,
+}
+
+/// Outcome of a command executed in a container
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecResult {
+    pub stdout: String,
+    pub stderr: String,
+    /// Exit code reported by the container runtime, absent on timeout
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    /// Output was cut at `maxOutputBytes`
+    pub truncated: bool,
+    /// The command was aborted after `timeoutSeconds`
+    pub timed_out: bool,
+}
+
+/// Query parameters for reading the logs of a pod
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LogQuery {
+    /// The container to read logs from, defaults to the only container of the pod
+    pub container: Option<String>,
+    /// Keep the connection open and stream new lines as they are written
+    pub follow: Option<bool>,
+    /// Stream followed logs as Server-Sent Events instead of a chunked text body
+    pub sse: Option<bool>,
+    /// Number of lines from the end of the log to show
+    pub tail_lines: Option<i64>,
+    /// Only show lines written in the last N seconds
+    pub since_seconds: Option<i64>,
+    /// Only show lines written after this RFC3339 timestamp, not together
+    /// with `sinceSeconds`
+    pub since_time: Option<String>,
+    /// Prefix every line with its RFC3339 timestamp
+    pub timestamps: Option<bool>,
+    /// Read the logs of the previous terminated container instance
+    pub previous: Option<bool>,
+    /// Stop after reading this many bytes
+    pub limit_bytes: Option<i64>,
+}
+
+/// Query parameters for opening an interactive terminal in a container
+#[derive(Deserialize)]
+pub struct TerminalParams {
+    /// The target container's name, if specific container is required
+    pub container: Option<String>,
+    /// The command to run, split on whitespace, defaults to `/bin/sh`
+    pub command: Option<String>,
+    /// Allocate a TTY for the command, defaults to `true`
+    pub tty: Option<bool>,
+}
+
+/// Control messages sent by a terminal client as WebSocket text frames,
+/// binary frames are written to stdin as they are
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TerminalMessage {
+    /// Text to be written to stdin
+    Stdin { data: String },
+    /// The client terminal was resized
+    Resize { cols: u16, rows: u16 },
+}
+
+/// Parameters for opening a local TCP listener that forwards to a pod port
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PortForwardParams {
+    /// Loopback address to listen on, defaults to `127.0.0.1`. The tunnel
+    /// is not authenticated, other addresses are refused.
+    pub address: Option<IpAddr>,
+    /// Port to listen on, defaults to a free port picked by the OS
+    pub local_port: Option<u16>,
+    /// Close the listener after this many seconds, defaults to one hour
+    pub ttl_seconds: Option<u64>,
+}
+
+/// A local TCP listener forwarding connections to a pod port
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PortForwardListener {
+    /// Id to close the listener by before its TTL runs out
+    pub id: String,
+    pub pod: String,
+    pub address: String,
+    pub pod_port: u16,
+    pub ttl_seconds: u64,
+}
+
+/// Query parameters for copying files into or out of a container
+#[derive(Deserialize)]
+pub struct CopyParams {
+    /// The target container's name, if specific container is required
+    pub container: Option<String>,
+    /// File or directory to download, or directory to extract uploads into
+    pub path: String,
+}
+
+/// Query parameters for tailing the logs of every pod matching a selector
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateLogQuery {
+    /// Label selector of the pods to follow, all pods of the namespace if empty
+    pub labels: Option<String>,
+    /// Only follow containers with this name
+    pub container: Option<String>,
+    /// Number of lines from the end of each log to start with
+    pub tail_lines: Option<i64>,
+    /// Only show lines written in the last N seconds
+    pub since_seconds: Option<i64>,
+}
+
+/// A single line of an aggregated log stream
+#[derive(Serialize)]
+pub struct LogLine {
+    pub pod: String,
+    pub container: String,
+    /// The log line, prefixed with its RFC3339 timestamp
+    pub line: String,
+}
+
+/// One row of a pod listing, close to `kubectl get pods -o wide`
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PodSummary {
+    pub name: Option<String>,
+    pub namespace: Option<String>,
+    pub labels: Option<BTreeMap<String, String>>,
+    pub phase: Option<String>,
+    /// The reason shown in the STATUS column, e.g. `CrashLoopBackOff`
+    pub status: String,
+    /// Ready containers out of all containers, e.g. `1/2`
+    pub ready: String,
+    pub restarts: i32,
+    pub containers: Vec<ContainerSummary>,
+    pub node: Option<String>,
+    pub pod_ip: Option<String>,
+    pub qos_class: Option<String>,
+    pub owner: Option<OwnerSummary>,
+    pub created_at: Option<String>,
+    /// Time since creation, e.g. `3d` or `45m`
+    pub age: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerSummary {
+    pub name: String,
+    pub image: String,
+    pub ready: bool,
+    pub restart_count: i32,
+    /// `Running`, `Waiting: <reason>` or `Terminated: <reason>`
+    pub state: String,
+}
+
+#[derive(Serialize)]
+pub struct OwnerSummary {
+    pub kind: String,
+    pub name: String,
+}
+
+/// Query parameters for reading a single pod
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PodInfoParams {
+    /// Drop `metadata.managedFields` from the returned object
+    pub strip_managed_fields: Option<bool>,
+}
+
+/// A pod object together with an explanation of why it is not ready
+#[derive(Serialize)]
+pub struct PodDetail {
+    #[serde(flatten)]
+    pub pod: Pod,
+    pub diagnosis: PodDiagnosis,
+    /// The most recent events of the pod
+    pub events: Vec<EventSummary>,
+}
+
+#[derive(Serialize, Default)]
+pub struct PodDiagnosis {
+    pub ready: bool,
+    pub problems: Vec<PodProblem>,
+}
+
+/// Something keeping a pod or one of its containers from becoming ready
+#[derive(Serialize)]
+pub struct PodProblem {
+    pub container: Option<String>,
+    /// Machine readable reason, e.g. `CrashLoopBackOff` or `Unschedulable`
+    pub reason: String,
+    pub message: String,
+}
+
+/// Parameters for adding an ephemeral debug container to a pod
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugParams {
+    /// Image of the debug container, defaults to busybox
+    pub image: Option<String>,
+    /// Container whose process namespace the debugger shares
+    pub target: Option<String>,
+    /// Command of the debug container, defaults to `sh`
+    pub command: Option<Vec<String>>,
+    /// Name of the debug container, generated if absent
+    pub name: Option<String>,
+    /// How long to wait for the container to start, defaults to 120
+    pub timeout_seconds: Option<u64>,
+}
+
+/// A started debug container and the endpoints to open a terminal in it
+#[derive(Serialize)]
+pub struct DebugSession {
+    pub pod: String,
+    pub container: String,
+    pub image: String,
+    /// WebSocket path attaching to the debug container's shell
+    pub attach: String,
+    /// WebSocket path running a new command in the debug container
+    pub exec: String,
 }
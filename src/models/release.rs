@@ -0,0 +1,65 @@
+use crate::models::manifest::{ApplyResult, DeleteResult};
+use serde::{Deserialize, Serialize};
+
+/// One revision of a release: a template rendered with a set of values and
+/// applied to a namespace
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Release {
+    pub name: String,
+    pub namespace: String,
+    pub revision: u32,
+    /// Template name as used by `create_deployment`, e.g. `nginx`
+    pub template: String,
+    pub values: serde_json::Value,
+    /// The rendered multi-document YAML
+    pub manifest: String,
+    /// `deployed`, `superseded` or `failed`
+    pub status: String,
+    pub updated: String,
+}
+
+/// A release revision without its values and manifest, for listings
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseSummary {
+    pub name: String,
+    pub revision: u32,
+    pub template: String,
+    pub status: String,
+    pub updated: String,
+}
+
+/// Request body to install a release
+#[derive(Deserialize)]
+pub struct InstallParams {
+    pub name: String,
+    pub template: String,
+    #[serde(default)]
+    pub values: serde_json::Value,
+}
+
+/// Request body to upgrade a release
+#[derive(Deserialize, Default)]
+pub struct UpgradeParams {
+    /// Switch to another template, the current one if absent
+    pub template: Option<String>,
+    /// New values, the current ones if absent
+    pub values: Option<serde_json::Value>,
+}
+
+/// Request body to roll a release back
+#[derive(Deserialize, Default)]
+pub struct ReleaseRollbackParams {
+    /// Revision to go back to, the one before the current if absent
+    pub revision: Option<u32>,
+}
+
+/// Outcome of installing, upgrading or rolling back a release
+#[derive(Serialize)]
+pub struct ReleaseReport {
+    pub release: ReleaseSummary,
+    pub results: Vec<ApplyResult>,
+    /// Objects of the previous revision the new one no longer has
+    pub pruned: Vec<DeleteResult>,
+}
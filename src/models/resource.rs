@@ -0,0 +1,21 @@
+use serde::Deserialize;
+
+/// Path of a generic resource request. `namespace` is absent for
+/// cluster-scoped paths and `name` for collection requests.
+#[derive(Deserialize)]
+pub struct ResourcePath {
+    /// API group, `core` for the legacy core group
+    pub group: String,
+    pub version: String,
+    pub namespace: Option<String>,
+    /// Plural resource name as served by the API, e.g. `cronjobs`
+    pub plural: String,
+    pub name: Option<String>,
+}
+
+/// Query parameters for listing generic resources
+#[derive(Deserialize, Default)]
+pub struct ResourceQuery {
+    pub labels: Option<String>,
+    pub fields: Option<String>,
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+/// A template of the catalog, described by its sidecar `<name>.catalog.json`
+#[derive(Serialize)]
+pub struct TemplateInfo {
+    /// Name to render the template by, e.g. `nginx`
+    pub name: String,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    /// JSON Schema of the values, absent if the template declares none
+    pub schema: Option<serde_json::Value>,
+    pub source: TemplateSource,
+}
+
+/// Where a template comes from. Uploaded ConfigMaps shadow the template
+/// directory, which shadows the templates embedded in the binary.
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateSource {
+    Embedded,
+    Directory,
+    ConfigMap,
+}
+
+/// A template to upload, kept in a ConfigMap of the template namespace
+#[derive(Deserialize)]
+pub struct TemplateUpload {
+    /// The Handlebars source of the multi-document manifest
+    pub template: String,
+    /// The catalog entry, as in a sidecar `<name>.catalog.json`
+    pub catalog: Option<serde_json::Value>,
+}
+
+/// A value that does not match the schema of its template
+#[derive(Serialize, Debug)]
+pub struct FieldError {
+    /// JSON pointer to the value, empty for the values as a whole
+    pub field: String,
+    pub message: String,
+}
+
+/// Query parameters for rendering a template without applying it
+#[derive(Deserialize, Default)]
+pub struct RenderParams {
+    /// `yaml` for the multi-document manifest, the default, or `json`
+    pub format: Option<String>,
+}
+
+/// A template rendered with a set of values
+#[derive(Serialize)]
+pub struct RenderedTemplate {
+    /// The multi-document YAML, in `yaml` format
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<String>,
+    /// Every document as JSON, in `json` format
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub documents: Option<Vec<serde_json::Value>>,
+    /// Documents that would not be accepted as their kind
+    pub errors: Vec<DocumentError>,
+}
+
+/// A rendered document that does not deserialise as its kind
+#[derive(Serialize)]
+pub struct DocumentError {
+    /// Position of the document in the manifest, from 0
+    pub index: usize,
+    pub kind: String,
+    pub name: String,
+    pub message: String,
+}
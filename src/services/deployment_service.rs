@@ -1,22 +1,22 @@
+use crate::models::deployment::{DeploymentDetail, PatchQuery};
+use crate::models::manifest::{
+    ApplyAction, ApplyResult, AtomicReport, DeleteAction, DeleteManifestParams, DeleteResult,
+    ManifestParams, RollbackAction, RollbackResult,
+};
+use crate::services::{event_service, resource_service, rollout_service, template_service};
 use crate::{Error, Result};
 use anyhow::anyhow;
 use axum::body::Bytes;
-use handlebars::Handlebars;
-use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
-use k8s_openapi::api::core::v1::{ConfigMap, Secret, Service};
-use k8s_openapi::api::networking::v1::Ingress;
-use kube::api::{DeleteParams, Patch, PatchParams, PostParams};
-use kube::{api::Api, Client};
+use futures::{stream, StreamExt};
+use k8s_openapi::api::apps::v1::Deployment;
+use kube::api::{DeleteParams, DynamicObject, Patch, PatchParams, PostParams, PropagationPolicy};
+use kube::{api::Api, Client, ResourceExt};
 use log::*;
-use rust_embed::RustEmbed;
 use serde::{Deserialize, Serialize};
+use similar::TextDiff;
 
 use std::sync::Arc;
 
-#[derive(RustEmbed)]
-#[folder = "src/web/templates/"]
-struct TemplateDirectory;
-
 #[derive(Serialize, Deserialize)]
 struct ProcessResult {
     success: Vec<String>,
@@ -27,17 +27,10 @@ pub(crate) async fn create_deployment_by_yaml(
     client: Arc<Client>,
     namespace: &str,
     body: &Bytes,
+    params: ManifestParams,
 ) -> Result<String> {
-    //把doc放到map里，然后再便利map执行创建资源操作试试
-    let mut container: Vec<serde_yaml::Value> = Vec::new();
-    for document in serde_yaml::Deserializer::from_slice(&body) {
-        let doc = serde_yaml::Value::deserialize(document).map_err(|e| Error::General(e.into()))?;
-        container.push(doc);
-    }
-    info!("Container length: {}", container.len());
-    let result = process_resources(container, namespace, client).await?;
-    let json_result = serde_json::to_string(&result).map_err(|e| Error::General(e.into()))?;
-    Ok(json_result)
+    let container = documents(body)?;
+    submit(container, namespace, client, &params).await
 }
 
 pub(crate) async fn create_deployment(
@@ -45,31 +38,74 @@ pub(crate) async fn create_deployment(
     namespace: &str,
     service_name: &str,
     data: serde_json::Value,
+    params: ManifestParams,
 ) -> Result<String> {
-    let mut handlebars = Handlebars::new();
-    let template_name = format!("{}.yaml.hbs", service_name);
-    let template_file = TemplateDirectory::get(&template_name).ok_or(Error::TemplateNotFound)?;
-    let template_str =
-        std::str::from_utf8(&template_file.data).map_err(|e| Error::General(e.into()))?;
-    info!("{}", template_str);
-    handlebars
-        .register_template_string(service_name, template_str)
-        .map_err(|e| Error::General(e.into()))?;
+    let rendered = template_service::render(service_name, &data)?;
+    let container = documents(rendered.as_bytes())?;
+    submit(container, namespace, client, &params).await
+}
 
-    let rendered = handlebars
-        .render(service_name, &data)
-        .map_err(|e| Error::General(e.into()))?;
-    info!("{}", rendered);
+/// Deletes every object of a YAML bundle, the inverse of
+/// `create_deployment_by_yaml`.
+pub(crate) async fn delete_deployment_by_yaml(
+    client: Arc<Client>,
+    namespace: &str,
+    body: &Bytes,
+    params: DeleteManifestParams,
+) -> Result<String> {
+    let container = documents(body)?;
+    let results = delete_resources(container, namespace, client, &params).await?;
+    serde_json::to_string(&results).map_err(|e| Error::General(e.into()))
+}
+
+/// Deletes every object a template renders to with `data`, the inverse of
+/// `create_deployment`.
+pub(crate) async fn uninstall_deployment(
+    client: Arc<Client>,
+    namespace: &str,
+    service_name: &str,
+    data: serde_json::Value,
+    params: DeleteManifestParams,
+) -> Result<String> {
+    let rendered = template_service::render(service_name, &data)?;
+    let container = documents(rendered.as_bytes())?;
+    let results = delete_resources(container, namespace, client, &params).await?;
+    serde_json::to_string(&results).map_err(|e| Error::General(e.into()))
+}
+
+pub(crate) fn documents(manifest: &[u8]) -> Result<Vec<serde_yaml::Value>> {
     //把doc放到map里，然后再便利map执行创建资源操作试试
     let mut container: Vec<serde_yaml::Value> = Vec::new();
-    for document in serde_yaml::Deserializer::from_str(&rendered) {
+    for document in serde_yaml::Deserializer::from_slice(manifest) {
         let doc = serde_yaml::Value::deserialize(document).map_err(|e| Error::General(e.into()))?;
         container.push(doc);
     }
     info!("Container length: {}", container.len());
-    let result = process_resources(container, namespace, client).await?;
-    let json_result = serde_json::to_string(&result).map_err(|e| Error::General(e.into()))?;
-    Ok(json_result)
+    Ok(container)
+}
+
+/// Creates or, in apply mode, server-side applies the documents of a
+/// bundle and returns the JSON report. Dry runs report a diff per document
+/// and leave the cluster untouched, atomic submissions are rolled back as a
+/// whole if any document fails.
+async fn submit(
+    container: Vec<serde_yaml::Value>,
+    namespace: &str,
+    client: Arc<Client>,
+    params: &ManifestParams,
+) -> Result<String> {
+    let dry_run = params.dry_run.unwrap_or_default();
+    let json_result = if params.atomic.unwrap_or_default() && !dry_run {
+        let report = apply_atomically(container, namespace, client, params).await;
+        serde_json::to_string(&report)
+    } else if params.apply.unwrap_or_default() || dry_run {
+        let results = apply_resources(container, namespace, client, params).await;
+        serde_json::to_string(&results)
+    } else {
+        let result = process_resources(container, namespace, client).await?;
+        serde_json::to_string(&result)
+    };
+    json_result.map_err(|e| Error::General(e.into()))
 }
 
 async fn process_resources(
@@ -77,11 +113,17 @@ async fn process_resources(
     namespace: &str,
     client: Arc<Client>,
 ) -> Result<ProcessResult> {
+    let client = client.as_ref();
     let mut success_results = Vec::new();
     let mut failure_results = Vec::new();
-    for doc in container {
-        if !doc.is_null() {
-            match process_resource(&doc, namespace, client.as_ref()).await {
+    for stage in dependency_stages(container) {
+        let outcomes: Vec<Result<String>> = stream::iter(stage)
+            .map(|doc| async move { process_resource(&doc, namespace, client).await })
+            .buffered(MAX_PARALLEL_DOCUMENTS)
+            .collect()
+            .await;
+        for outcome in outcomes {
+            match outcome {
                 Ok(msg) => {
                     success_results.push(msg);
                 }
@@ -98,193 +140,553 @@ async fn process_resources(
     Ok(result)
 }
 
+/// Documents of a bundle handled at the same time, within one stage
+const MAX_PARALLEL_DOCUMENTS: usize = 8;
+
+/// Kinds in the order their documents are applied. Kinds of one stage do
+/// not depend on each other, kinds missing here go last.
+const KIND_STAGES: &[&[&str]] = &[
+    &["Namespace"],
+    &["CustomResourceDefinition"],
+    &["ServiceAccount"],
+    &["ClusterRole", "ClusterRoleBinding", "Role", "RoleBinding"],
+    &[
+        "ConfigMap",
+        "Secret",
+        "StorageClass",
+        "PersistentVolume",
+        "PersistentVolumeClaim",
+    ],
+    &["Service"],
+    &[
+        "Deployment",
+        "StatefulSet",
+        "DaemonSet",
+        "ReplicaSet",
+        "Pod",
+        "Job",
+        "CronJob",
+        "HorizontalPodAutoscaler",
+        "PodDisruptionBudget",
+    ],
+    &["Ingress"],
+];
+
+/// Groups the documents of a bundle into stages in dependency order, keeping
+/// the file order within each stage. Null documents are dropped.
+fn dependency_stages(container: Vec<serde_yaml::Value>) -> Vec<Vec<serde_yaml::Value>> {
+    let mut stages = vec![Vec::new(); KIND_STAGES.len() + 1];
+    for doc in container {
+        if doc.is_null() {
+            continue;
+        }
+        let kind = doc["kind"].as_str().unwrap_or_default();
+        let stage = KIND_STAGES
+            .iter()
+            .position(|kinds| kinds.contains(&kind))
+            .unwrap_or(KIND_STAGES.len());
+        stages[stage].push(doc);
+    }
+    stages.retain(|stage| !stage.is_empty());
+    stages
+}
+
 async fn process_resource(
     doc: &serde_yaml::Value,
     namespace: &str,
     client: &Client,
 ) -> Result<String> {
+    let created = create_resource(doc, namespace, client).await?;
+    let resource_type = doc["kind"].as_str().unwrap_or_default();
+    Ok(format!("Created {}: {}", resource_type, created.name))
+}
+
+/// A document that changed the cluster, with what it takes to undo it
+struct Applied {
+    api: Api<DynamicObject>,
+    name: String,
+    /// The object before the change, absent if the document created it
+    previous: Option<DynamicObject>,
+    action: ApplyAction,
+}
+
+async fn create_resource(
+    doc: &serde_yaml::Value,
+    namespace: &str,
+    client: &Client,
+) -> Result<Applied> {
+    let (api, data) = dynamic_resource(doc, namespace, client).await?;
+    let pp = PostParams::default();
+    match api.create(&pp, &data).await {
+        Ok(resource) => Ok(Applied {
+            name: resource.name_any(),
+            api,
+            previous: None,
+            action: ApplyAction::Created,
+        }),
+        Err(e) => Err(create_error(e, &data)),
+    }
+}
+
+/// Resolves the kind of a document through discovery and returns an API
+/// for it together with the document as a dynamic object.
+async fn dynamic_resource(
+    doc: &serde_yaml::Value,
+    namespace: &str,
+    client: &Client,
+) -> Result<(Api<DynamicObject>, DynamicObject)> {
     let resource_type = doc["kind"]
         .as_str()
         .ok_or_else(|| Error::General(anyhow!("Missing 'kind' field")))?;
-    match resource_type {
-        "Deployment" => {
-            let api: Api<Deployment> = Api::namespaced(client.clone(), namespace);
-            let data = serde_yaml::from_value::<Deployment>(doc.clone())
-                .map_err(|e| Error::General(e.into()))?;
-            let pp = PostParams::default();
-            match api.create(&pp, &data).await {
-                Ok(resource) => Ok(format!(
-                    "Created Deployment: {}",
-                    resource.metadata.name.unwrap()
-                )),
-                Err(kube::Error::Api(ae)) => {
-                    if ae.code == 409 {
-                        Err(Error::ResourceAlreadyExists(anyhow!(
-                            "Resource {} {} already exists",
-                            resource_type,
-                            data.metadata.name.unwrap()
-                        )))
-                    } else {
-                        Err(Error::General(anyhow::Error::new(ae)))
-                    }
-                }
-                Err(e) => Err(e.into()),
+    let api_version = doc["apiVersion"]
+        .as_str()
+        .ok_or_else(|| Error::General(anyhow!("Missing 'apiVersion' field")))?;
+    let api = resource_service::api_for_kind(client, namespace, api_version, resource_type).await?;
+    let data = serde_yaml::from_value::<DynamicObject>(doc.clone())
+        .map_err(|e| Error::General(e.into()))?;
+    Ok((api, data))
+}
+
+fn create_error(err: kube::Error, data: &DynamicObject) -> Error {
+    match err {
+        kube::Error::Api(ae) => {
+            if ae.code == 409 {
+                Error::ResourceAlreadyExists(anyhow!(
+                    "Resource {} {} already exists",
+                    data.types
+                        .as_ref()
+                        .map(|t| t.kind.as_str())
+                        .unwrap_or_default(),
+                    data.name_any()
+                ))
+            } else {
+                Error::General(anyhow::Error::new(ae))
             }
         }
-        "StatefulSet" => {
-            let api: Api<StatefulSet> = Api::namespaced(client.clone(), namespace);
-            let data: StatefulSet = serde_yaml::from_value::<StatefulSet>(doc.clone())
-                .map_err(|e| Error::General(e.into()))?;
-            let pp = PostParams::default();
-            match api.create(&pp, &data).await {
-                Ok(resource) => Ok(format!(
-                    "Created StatefulSet: {}",
-                    resource.metadata.name.unwrap()
-                )),
-                Err(kube::Error::Api(ae)) => {
-                    if ae.code == 409 {
-                        Err(Error::ResourceAlreadyExists(anyhow!(
-                            "Resource {} {} already exists",
-                            resource_type,
-                            data.metadata.name.unwrap()
-                        )))
-                    } else {
-                        Err(Error::General(anyhow::Error::new(ae)))
-                    }
+        e => e.into(),
+    }
+}
+
+/// Applies, or with `dryRun` previews, every document of a bundle in
+/// dependency order and reports the outcome of each.
+pub(crate) async fn apply_resources(
+    container: Vec<serde_yaml::Value>,
+    namespace: &str,
+    client: Arc<Client>,
+    params: &ManifestParams,
+) -> Vec<ApplyResult> {
+    let client = client.as_ref();
+    let mut results = Vec::new();
+    for stage in dependency_stages(container) {
+        let outcomes: Vec<(ApplyResult, Option<Applied>)> = stream::iter(stage)
+            .map(|doc| async move { apply_document(&doc, namespace, client, params).await })
+            .buffered(MAX_PARALLEL_DOCUMENTS)
+            .collect()
+            .await;
+        results.extend(outcomes.into_iter().map(|(result, _)| result));
+    }
+    results
+}
+
+/// Applies a bundle all or nothing. Once a document fails no further stage
+/// is started, objects created so far are deleted and updated ones are put
+/// back the way they were.
+async fn apply_atomically(
+    container: Vec<serde_yaml::Value>,
+    namespace: &str,
+    client: Arc<Client>,
+    params: &ManifestParams,
+) -> AtomicReport {
+    let client = client.as_ref();
+    let mut results = Vec::new();
+    let mut changes = Vec::new();
+    let mut failed = false;
+    for stage in dependency_stages(container) {
+        if failed {
+            results.extend(stage.iter().map(|doc| {
+                ApplyResult {
+                    kind: doc["kind"].as_str().unwrap_or_default().to_owned(),
+                    name: doc["metadata"]["name"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_owned(),
+                    action: None,
+                    diff: None,
+                    error: Some("Not applied, an earlier document failed".to_owned()),
                 }
-                Err(e) => Err(e.into()),
-            }
+            }));
+            continue;
         }
-        "Service" => {
-            let api: Api<Service> = Api::namespaced(client.clone(), namespace);
-            let data: Service = serde_yaml::from_value::<Service>(doc.clone())
-                .map_err(|e| Error::General(e.into()))?;
-            let pp = PostParams::default();
-            match api.create(&pp, &data).await {
-                Ok(resource) => Ok(format!(
-                    "Created Service: {}",
-                    resource.metadata.name.unwrap()
-                )),
-                Err(kube::Error::Api(ae)) => {
-                    if ae.code == 409 {
-                        Err(Error::ResourceAlreadyExists(anyhow!(
-                            "Resource {} {} already exists",
-                            resource_type,
-                            data.metadata.name.unwrap()
-                        )))
-                    } else {
-                        Err(Error::General(anyhow::Error::new(ae)))
-                    }
+        let outcomes: Vec<(ApplyResult, Option<Applied>)> = stream::iter(stage)
+            .map(|doc| async move { apply_document(&doc, namespace, client, params).await })
+            .buffered(MAX_PARALLEL_DOCUMENTS)
+            .collect()
+            .await;
+        for (result, applied) in outcomes {
+            failed |= result.error.is_some();
+            if let Some(applied) = applied {
+                if applied.action != ApplyAction::Unchanged {
+                    changes.push((result.kind.clone(), applied));
                 }
-                Err(e) => Err(e.into()),
             }
+            results.push(result);
         }
-        "ConfigMap" => {
-            let api: Api<ConfigMap> = Api::namespaced(client.clone(), namespace);
-            let data = serde_yaml::from_value::<ConfigMap>(doc.clone())
-                .map_err(|e| Error::General(e.into()))?;
-            let pp = PostParams::default();
-            match api.create(&pp, &data).await {
-                Ok(resource) => Ok(format!(
-                    "Created ConfigMap: {}",
-                    resource.metadata.name.unwrap()
-                )),
-                Err(e) => Err(e.into()),
-            }
+    }
+
+    let mut rolled_back = Vec::new();
+    if failed {
+        warn!("Bundle failed, rolling back {} changes", changes.len());
+        for (kind, applied) in changes.iter().rev() {
+            let outcome = undo(applied).await;
+            rolled_back.push(RollbackResult {
+                kind: kind.clone(),
+                name: applied.name.clone(),
+                action: outcome.as_ref().ok().copied(),
+                error: outcome.err().map(|e| e.to_string()),
+            });
         }
-        "Secret" => {
-            let api: Api<Secret> = Api::namespaced(client.clone(), namespace);
-            let data = serde_yaml::from_value::<Secret>(doc.clone())
-                .map_err(|e| Error::General(e.into()))?;
-            let pp = PostParams::default();
-            match api.create(&pp, &data).await {
-                Ok(resource) => Ok(format!(
-                    "Created Secret: {}",
-                    resource.metadata.name.unwrap()
-                )),
-                Err(e) => Err(e.into()),
-            }
+    }
+    AtomicReport {
+        succeeded: !failed,
+        results,
+        rolled_back,
+    }
+}
+
+/// Deletes what a document created or restores what it updated.
+async fn undo(applied: &Applied) -> Result<RollbackAction> {
+    match &applied.previous {
+        None => {
+            applied
+                .api
+                .delete(&applied.name, &DeleteParams::background())
+                .await?;
+            info!("Rolled back {} by deleting it", applied.name);
+            Ok(RollbackAction::Deleted)
         }
-        "Ingress" => {
-            let api: Api<Ingress> = Api::namespaced(client.clone(), namespace);
-            let data = serde_yaml::from_value::<Ingress>(doc.clone())
-                .map_err(|e| Error::General(e.into()))?;
-            let pp = PostParams::default();
-            match api.create(&pp, &data).await {
-                Ok(resource) => Ok(format!(
-                    "Created Ingress: {}",
-                    resource.metadata.name.unwrap()
-                )),
-                Err(e) => Err(e.into()),
-            }
+        Some(previous) => {
+            let current = applied.api.get(&applied.name).await?;
+            let mut restored = previous.clone();
+            restored.metadata.resource_version = current.metadata.resource_version;
+            restored.metadata.managed_fields = None;
+            applied
+                .api
+                .replace(&applied.name, &PostParams::default(), &restored)
+                .await?;
+            info!("Rolled back {} by restoring it", applied.name);
+            Ok(RollbackAction::Restored)
         }
-        _ => Err(Error::UnsupportedKind),
     }
 }
 
-pub async fn get_deployment(
-    client: Arc<Client>,
+/// Handles one document the way `params` asks for. Changes to the cluster
+/// are returned alongside the report so they can be undone.
+async fn apply_document(
+    doc: &serde_yaml::Value,
     namespace: &str,
-    deployment_name: &str,
-) -> Result<String> {
-    let deployment_api: Api<Deployment> = Api::namespaced(client.as_ref().clone(), namespace);
-    match deployment_api.get(deployment_name).await {
-        Ok(deployment) => {
-            let status = deployment.status.as_ref().ok_or(Error::ResourceNotFound)?;
-            let available_replicas = status.available_replicas.unwrap_or_default();
-            let replicas = status.replicas.unwrap_or_default();
-            Ok(format!(
-                "Deployment {} has {} available out of {} replicas",
-                deployment_name, available_replicas, replicas
-            ))
+    client: &Client,
+    params: &ManifestParams,
+) -> (ApplyResult, Option<Applied>) {
+    let kind = doc["kind"].as_str().unwrap_or_default().to_owned();
+    let name = doc["metadata"]["name"]
+        .as_str()
+        .unwrap_or_default()
+        .to_owned();
+    let outcome = if params.dry_run.unwrap_or_default() {
+        preview_resource(doc, namespace, client, params)
+            .await
+            .map(|(action, diff)| (action, diff, None))
+    } else if params.apply.unwrap_or_default() {
+        let force = params.force.unwrap_or_default();
+        apply_resource(doc, namespace, client, force)
+            .await
+            .map(|applied| (applied.action, None, Some(applied)))
+    } else {
+        create_resource(doc, namespace, client)
+            .await
+            .map(|applied| (applied.action, None, Some(applied)))
+    };
+    match outcome {
+        Ok((action, diff, applied)) => {
+            let result = ApplyResult {
+                kind,
+                name,
+                action: Some(action),
+                diff,
+                error: None,
+            };
+            (result, applied)
+        }
+        Err(err) => {
+            let result = ApplyResult {
+                kind,
+                name,
+                action: None,
+                diff: None,
+                error: Some(err.to_string()),
+            };
+            (result, None)
         }
-        Err(e) => Err(e.into()),
     }
 }
 
-pub(crate) async fn patch_deployment(
+/// Server-side applies a document as the kube-sphere field manager. Whether
+/// the object changed is told by its resource version, which the API server
+/// leaves alone for no-op applies.
+async fn apply_resource(
+    doc: &serde_yaml::Value,
+    namespace: &str,
+    client: &Client,
+    force: bool,
+) -> Result<Applied> {
+    let (api, data) = dynamic_resource(doc, namespace, client).await?;
+    let name = name_of(&data)?;
+    let existing = api.get_opt(&name).await?;
+    let mut pp = PatchParams::apply(resource_service::FIELD_MANAGER);
+    if force {
+        pp = pp.force();
+    }
+    let applied = api.patch(&name, &pp, &Patch::Apply(&data)).await?;
+    let action = match &existing {
+        None => ApplyAction::Created,
+        Some(existing) if existing.resource_version() == applied.resource_version() => {
+            ApplyAction::Unchanged
+        }
+        Some(_) => ApplyAction::Configured,
+    };
+    info!(
+        "Applied {} {}: {:?}",
+        doc["kind"].as_str().unwrap_or_default(),
+        name,
+        action
+    );
+    Ok(Applied {
+        api,
+        name,
+        previous: existing,
+        action,
+    })
+}
+
+/// Deletes the live objects of a bundle in reverse dependency order, so
+/// workloads go before the configuration and namespaces they use.
+pub(crate) async fn delete_resources(
+    container: Vec<serde_yaml::Value>,
+    namespace: &str,
     client: Arc<Client>,
+    params: &DeleteManifestParams,
+) -> Result<Vec<DeleteResult>> {
+    let propagation_policy = match params.propagation_policy.as_deref() {
+        None => None,
+        Some("Foreground") => Some(PropagationPolicy::Foreground),
+        Some("Background") => Some(PropagationPolicy::Background),
+        Some("Orphan") => Some(PropagationPolicy::Orphan),
+        Some(other) => {
+            return Err(Error::BadRequest(format!(
+                "Unsupported propagation policy {}, expected Foreground, Background or Orphan",
+                other
+            )))
+        }
+    };
+    let dp = DeleteParams {
+        propagation_policy,
+        ..DeleteParams::default()
+    };
+
+    let (client, dp) = (client.as_ref(), &dp);
+    let mut results = Vec::new();
+    for stage in dependency_stages(container).into_iter().rev() {
+        let outcomes: Vec<DeleteResult> = stream::iter(stage)
+            .map(|doc| async move { delete_document(&doc, namespace, client, dp).await })
+            .buffered(MAX_PARALLEL_DOCUMENTS)
+            .collect()
+            .await;
+        results.extend(outcomes);
+    }
+    Ok(results)
+}
+
+async fn delete_document(
+    doc: &serde_yaml::Value,
     namespace: &str,
-    deployment_name: &str,
-    data: serde_json::Value,
-) -> Result<String> {
-    let deployment_api: Api<Deployment> = Api::namespaced(client.as_ref().clone(), &namespace);
-    match deployment_api.get(&deployment_name).await {
+    client: &Client,
+    dp: &DeleteParams,
+) -> DeleteResult {
+    let kind = doc["kind"].as_str().unwrap_or_default().to_owned();
+    let name = doc["metadata"]["name"]
+        .as_str()
+        .unwrap_or_default()
+        .to_owned();
+    match delete_resource(doc, namespace, client, dp).await {
+        Ok(action) => DeleteResult {
+            kind,
+            name,
+            action: Some(action),
+            error: None,
+        },
+        Err(err) => DeleteResult {
+            kind,
+            name,
+            action: None,
+            error: Some(err.to_string()),
+        },
+    }
+}
+
+async fn delete_resource(
+    doc: &serde_yaml::Value,
+    namespace: &str,
+    client: &Client,
+    dp: &DeleteParams,
+) -> Result<DeleteAction> {
+    let (api, data) = dynamic_resource(doc, namespace, client).await?;
+    let name = name_of(&data)?;
+    match api.delete(&name, dp).await {
         Ok(_) => {
             info!(
-                "deployment {} was found in namespace {}",
-                deployment_name, namespace
+                "Deleted {} {}",
+                doc["kind"].as_str().unwrap_or_default(),
+                name
             );
-            // 发送 Patch 请求来更新 Deployment
-            let patch_params = PatchParams::default();
-            match deployment_api
-                .patch(&deployment_name, &patch_params, &Patch::Merge(&data))
-                .await
-            {
-                Ok(_updated_deployment) => {
-                    info!("Deployment updated: {}", deployment_name);
-                    Ok("OK".to_owned())
-                }
-                Err(e) => Err(e.into()),
-            }
+            Ok(DeleteAction::Deleted)
         }
+        Err(kube::Error::Api(ae)) if ae.code == 404 => Ok(DeleteAction::NotFound),
         Err(e) => Err(e.into()),
     }
 }
 
+/// Sends a document through a server-side dry run, as a create or an apply
+/// like the real submission would be, and diffs the object the server would
+/// store against the live one. A create of an object that already exists
+/// fails with a conflict, as the real create does.
+async fn preview_resource(
+    doc: &serde_yaml::Value,
+    namespace: &str,
+    client: &Client,
+    params: &ManifestParams,
+) -> Result<(ApplyAction, Option<String>)> {
+    let (api, data) = dynamic_resource(doc, namespace, client).await?;
+    let name = name_of(&data)?;
+    let live = api.get_opt(&name).await?;
+    let apply = params.apply.unwrap_or_default();
+    let dry_run = if apply {
+        let mut pp = PatchParams::apply(resource_service::FIELD_MANAGER).dry_run();
+        if params.force.unwrap_or_default() {
+            pp = pp.force();
+        }
+        api.patch(&name, &pp, &Patch::Apply(&data)).await?
+    } else {
+        let pp = PostParams {
+            dry_run: true,
+            ..PostParams::default()
+        };
+        api.create(&pp, &data)
+            .await
+            .map_err(|e| create_error(e, &data))?
+    };
+
+    let before = match &live {
+        Some(live) => comparable(live)?,
+        None => String::new(),
+    };
+    let after = comparable(&dry_run)?;
+    let action = match live {
+        None => ApplyAction::Created,
+        Some(_) if before == after => ApplyAction::Unchanged,
+        Some(_) => ApplyAction::Configured,
+    };
+    if action == ApplyAction::Unchanged {
+        return Ok((action, None));
+    }
+    let diff = TextDiff::from_lines(&before, &after)
+        .unified_diff()
+        .header("live", "dry-run")
+        .to_string();
+    Ok((action, Some(diff)))
+}
+
+/// Written by `kubectl apply`, it repeats the whole object
+const LAST_APPLIED_ANNOTATION: &str = "kubectl.kubernetes.io/last-applied-configuration";
+
+/// An object as YAML without the status and the bookkeeping fields the API
+/// server rewrites on every write, so diffs show only real changes.
+fn comparable(object: &DynamicObject) -> Result<String> {
+    let mut value = serde_json::to_value(object)?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("status");
+    }
+    if let Some(metadata) = value["metadata"].as_object_mut() {
+        for field in [
+            "managedFields",
+            "resourceVersion",
+            "generation",
+            "uid",
+            "creationTimestamp",
+        ] {
+            metadata.remove(field);
+        }
+        if let Some(annotations) = metadata
+            .get_mut("annotations")
+            .and_then(|annotations| annotations.as_object_mut())
+        {
+            annotations.remove(LAST_APPLIED_ANNOTATION);
+            if annotations.is_empty() {
+                metadata.remove("annotations");
+            }
+        }
+    }
+    serde_yaml::to_string(&value).map_err(|e| Error::General(e.into()))
+}
+
+fn name_of(data: &DynamicObject) -> Result<String> {
+    data.metadata
+        .name
+        .clone()
+        .ok_or_else(|| Error::General(anyhow!("Missing 'metadata.name' field")))
+}
+
+pub async fn get_deployment(
+    client: Arc<Client>,
+    namespace: &str,
+    deployment_name: &str,
+) -> Result<DeploymentDetail> {
+    let rollout =
+        rollout_service::status::<Deployment>(client.clone(), namespace, deployment_name).await?;
+    let events = event_service::for_object(client, namespace, "Deployment", deployment_name).await;
+    Ok(DeploymentDetail { rollout, events })
+}
+
+/// Patches a deployment with a merge, strategic merge, RFC 6902 JSON or
+/// server-side apply patch. The patch type comes from the `type` query
+/// parameter or else the request content type, and defaults to merge.
+pub(crate) async fn patch_deployment(
+    client: Arc<Client>,
+    namespace: &str,
+    deployment_name: &str,
+    query: PatchQuery,
+    content_type: Option<&str>,
+    body: &[u8],
+) -> Result<Deployment> {
+    let deployment_api: Api<Deployment> = Api::namespaced(client.as_ref().clone(), namespace);
+    // 发送 Patch 请求来更新 Deployment
+    let (patch_params, patch) = resource_service::patch_request(query, content_type, body)?;
+    let updated = deployment_api
+        .patch(deployment_name, &patch_params, &patch)
+        .await?;
+    info!("Deployment updated: {}", deployment_name);
+    Ok(updated)
+}
+
 pub(crate) async fn delete_deployment(
     client: Arc<Client>,
     namespace: &str,
     deployment_name: &str,
 ) -> Result<String> {
-    let deployment_api: Api<Deployment> = Api::namespaced(client.as_ref().clone(), &namespace);
+    let deployment_api: Api<Deployment> = Api::namespaced(client.as_ref().clone(), namespace);
     // 创建 Delete 请求参数
     let delete_params = DeleteParams::default();
     // 发送 Delete 请求来删除 Deployment
-    match deployment_api
-        .delete(&deployment_name, &delete_params)
-        .await
-    {
+    match deployment_api.delete(deployment_name, &delete_params).await {
         Ok(_) => {
             info!("Deployment deleted: {}", deployment_name);
             Ok("OK".to_owned())
@@ -292,3 +694,100 @@ pub(crate) async fn delete_deployment(
         Err(err) => Err(err.into()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comparable_drops_server_bookkeeping() {
+        let live: DynamicObject = serde_json::from_value(serde_json::json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {
+                "name": "web",
+                "uid": "0b6c1a52",
+                "generation": 3,
+                "resourceVersion": "42",
+                "creationTimestamp": "2023-01-01T00:00:00Z",
+                "managedFields": [{ "manager": "kubectl" }],
+                "annotations": { LAST_APPLIED_ANNOTATION: "{}" },
+            },
+            "spec": { "replicas": 2 },
+            "status": { "replicas": 2, "readyReplicas": 1 },
+        }))
+        .unwrap();
+        let desired: DynamicObject = serde_json::from_value(serde_json::json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "name": "web" },
+            "spec": { "replicas": 2 },
+        }))
+        .unwrap();
+        assert_eq!(comparable(&live).unwrap(), comparable(&desired).unwrap());
+    }
+
+    fn documents(manifest: &str) -> Vec<serde_yaml::Value> {
+        serde_yaml::Deserializer::from_str(manifest)
+            .map(|doc| serde_yaml::Value::deserialize(doc).unwrap())
+            .collect()
+    }
+
+    fn names(stages: &[Vec<serde_yaml::Value>]) -> Vec<Vec<&str>> {
+        stages
+            .iter()
+            .map(|stage| {
+                stage
+                    .iter()
+                    .map(|doc| doc["metadata"]["name"].as_str().unwrap())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dependencies_are_created_before_their_dependents() {
+        let stages = dependency_stages(documents(
+            "kind: Ingress\nmetadata:\n  name: web\n\
+             ---\nkind: Deployment\nmetadata:\n  name: api\n\
+             ---\nkind: Service\nmetadata:\n  name: api\n\
+             ---\nkind: ConfigMap\nmetadata:\n  name: settings\n\
+             ---\nkind: Namespace\nmetadata:\n  name: shop\n",
+        ));
+        assert_eq!(
+            names(&stages),
+            vec![
+                vec!["shop"],
+                vec!["settings"],
+                vec!["api"],
+                vec!["api"],
+                vec!["web"]
+            ]
+        );
+        assert_eq!(stages[2][0]["kind"], "Service");
+    }
+
+    #[test]
+    fn file_order_is_kept_within_a_stage() {
+        let stages = dependency_stages(documents(
+            "kind: StatefulSet\nmetadata:\n  name: db\n\
+             ---\nkind: Secret\nmetadata:\n  name: password\n\
+             ---\nkind: Deployment\nmetadata:\n  name: api\n\
+             ---\nkind: ConfigMap\nmetadata:\n  name: settings\n",
+        ));
+        assert_eq!(
+            names(&stages),
+            vec![vec!["password", "settings"], vec!["db", "api"]]
+        );
+    }
+
+    #[test]
+    fn unknown_kinds_go_last_and_empty_documents_are_dropped() {
+        let stages = dependency_stages(documents(
+            "kind: Certificate\nmetadata:\n  name: tls\n\
+             ---\n\
+             ---\nkind: Pod\nmetadata:\n  name: debug\n",
+        ));
+        assert_eq!(names(&stages), vec![vec!["debug"], vec!["tls"]]);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::models::event::{EventQuery, EventSummary};
+use crate::services::pod_service::human_age;
+use crate::Result;
+use futures::{future, Stream, StreamExt};
+use k8s_openapi::api::core::v1::Event;
+use k8s_openapi::chrono::{DateTime, SecondsFormat, Utc};
+use kube::api::{Api, ListParams};
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::Client;
+use log::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Lists the events of a namespace matching `query`, most recent first,
+/// with repeated occurrences of the same event folded into one entry.
+pub async fn list(
+    client: Arc<Client>,
+    namespace: &str,
+    query: &EventQuery,
+) -> Result<Vec<EventSummary>> {
+    let event_api: Api<Event> = Api::namespaced(client.as_ref().clone(), namespace);
+    let events = event_api.list(&list_params(query)).await?;
+    info!(
+        "Found {} events in namespace {}",
+        events.items.len(),
+        namespace
+    );
+
+    // occurrences are folded and ordered on their times, not on the
+    // formatted strings, so events without a timestamp sort last
+    let mut folded: HashMap<(String, Option<String>, Option<String>), Folded> = HashMap::new();
+    for event in &events.items {
+        let summary = summarize(event);
+        let (first_seen, last_seen) = seen(event);
+        let key = (
+            summary.object.clone(),
+            summary.reason.clone(),
+            summary.message.clone(),
+        );
+        match folded.get_mut(&key) {
+            Some(existing) => {
+                existing.summary.count += summary.count;
+                let earlier = match (first_seen, existing.first_seen) {
+                    (Some(time), Some(existing_time)) => time < existing_time,
+                    (Some(_), None) => true,
+                    (None, _) => false,
+                };
+                if earlier {
+                    existing.first_seen = first_seen;
+                    existing.summary.first_seen = summary.first_seen;
+                }
+                if last_seen > existing.last_seen {
+                    existing.last_seen = last_seen;
+                    existing.summary.last_seen = summary.last_seen;
+                    existing.summary.age = summary.age;
+                }
+            }
+            None => {
+                folded.insert(
+                    key,
+                    Folded {
+                        summary,
+                        first_seen,
+                        last_seen,
+                    },
+                );
+            }
+        }
+    }
+
+    let mut folded: Vec<Folded> = folded.into_values().collect();
+    folded.sort_by_key(|f| std::cmp::Reverse(f.last_seen));
+    let mut summaries: Vec<EventSummary> = folded.into_iter().map(|f| f.summary).collect();
+    if let Some(limit) = query.limit {
+        summaries.truncate(limit);
+    }
+    Ok(summaries)
+}
+
+/// The most recent events of a single object, for detail responses.
+pub async fn for_object(
+    client: Arc<Client>,
+    namespace: &str,
+    kind: &str,
+    name: &str,
+) -> Vec<EventSummary> {
+    let query = EventQuery {
+        kind: Some(kind.to_owned()),
+        name: Some(name.to_owned()),
+        limit: Some(10),
+        ..Default::default()
+    };
+    // events are a debugging aid, losing them must not fail the detail request
+    list(client, namespace, &query).await.unwrap_or_else(|e| {
+        warn!(
+            "Cannot list events of {} {} in namespace {}: {}",
+            kind, name, namespace, e
+        );
+        Vec::new()
+    })
+}
+
+/// Streams events matching `query` as they are recorded. Events that
+/// already existed when the watch started are skipped.
+pub async fn watch(
+    client: Arc<Client>,
+    namespace: &str,
+    query: &EventQuery,
+) -> Result<impl Stream<Item = EventSummary> + Send> {
+    let event_api: Api<Event> = Api::namespaced(client.as_ref().clone(), namespace);
+    let lp = list_params(query);
+    // fail early on a bad selector instead of in the middle of the stream
+    event_api.list(&lp.clone().limit(1)).await?;
+    info!("Watching events in namespace {}", namespace);
+
+    let config =
+        watcher::Config::default().fields(lp.field_selector.as_deref().unwrap_or_default());
+    let events = watcher(event_api, config)
+        .backoff(watcher::default_backoff())
+        .filter_map(|event| {
+            future::ready(match event {
+                Ok(watcher::Event::Applied(event)) => Some(summarize(&event)),
+                Ok(_) => None,
+                Err(e) => {
+                    warn!("Watching events failed: {}", e);
+                    None
+                }
+            })
+        });
+    Ok(events)
+}
+
+fn list_params(query: &EventQuery) -> ListParams {
+    let mut fields = Vec::new();
+    if let Some(kind) = &query.kind {
+        fields.push(format!("involvedObject.kind={}", kind));
+    }
+    if let Some(name) = &query.name {
+        fields.push(format!("involvedObject.name={}", name));
+    }
+    if let Some(type_) = &query.type_ {
+        fields.push(format!("type={}", type_));
+    }
+    ListParams::default().fields(&fields.join(","))
+}
+
+fn summarize(event: &Event) -> EventSummary {
+    let (first_seen, last_seen) = seen(event);
+    let object = &event.involved_object;
+
+    EventSummary {
+        type_: event.type_.clone(),
+        reason: event.reason.clone(),
+        message: event.message.clone(),
+        object: format!(
+            "{}/{}",
+            object.kind.as_deref().unwrap_or_default(),
+            object.name.as_deref().unwrap_or_default()
+        ),
+        count: event.count.unwrap_or(1),
+        source: event
+            .source
+            .as_ref()
+            .and_then(|source| source.component.clone())
+            .or_else(|| event.reporting_component.clone()),
+        first_seen: first_seen.map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        last_seen: last_seen.map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        age: last_seen.map(human_age),
+    }
+}
+
+/// When an event was first and last seen, falling back to the event time and
+/// then to the creation of the Event object.
+fn seen(event: &Event) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+    let created = event
+        .metadata
+        .creation_timestamp
+        .as_ref()
+        .map(|time| time.0);
+    let event_time = event.event_time.as_ref().map(|time| time.0);
+    let first_seen: Option<DateTime<Utc>> = event
+        .first_timestamp
+        .as_ref()
+        .map(|time| time.0)
+        .or(event_time)
+        .or(created);
+    let last_seen: Option<DateTime<Utc>> = event
+        .last_timestamp
+        .as_ref()
+        .map(|time| time.0)
+        .or(event_time)
+        .or(created);
+    (first_seen, last_seen)
+}
+
+/// A folded event and the times it was first and last seen
+struct Folded {
+    summary: EventSummary,
+    first_seen: Option<DateTime<Utc>>,
+    last_seen: Option<DateTime<Utc>>,
+}
@@ -2,4 +2,9 @@ pub mod deployment_service;
 pub mod pod_service;
 pub mod namespace_service;
 pub mod configmap_service;
-pub mod ingress_service;
\ No newline at end of file
+pub mod ingress_service;
+pub mod event_service;
+pub mod rollout_service;
+pub mod resource_service;
+pub mod release_service;
+pub mod template_service;
\ No newline at end of file
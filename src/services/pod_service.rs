@@ -1,107 +1,1398 @@
 use crate::models::pod;
+use crate::services::event_service;
 use crate::{Error, Result};
-use futures::StreamExt;
-use k8s_openapi::api::core::v1::Pod;
+use anyhow::anyhow;
+use axum::body::Bytes;
+use axum::extract::ws::{Message, WebSocket};
+use futures::stream::{self, BoxStream, SplitSink, SplitStream};
+use futures::{AsyncBufReadExt, SinkExt, Stream, StreamExt, TryStreamExt};
+use k8s_openapi::api::core::v1::{ContainerState, ContainerStatus, Pod};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Status;
+use k8s_openapi::chrono::{DateTime, Utc};
 use kube::api::DeleteParams;
+use kube::api::{Patch, PatchParams};
+use kube::runtime::wait::await_condition;
+use kube::runtime::{watcher, WatchStreamExt};
 use kube::{
-    api::{AttachParams, AttachedProcess, ListParams, LogParams},
+    api::{AttachParams, AttachedProcess, ListParams, LogParams, TerminalSize},
     Api, Client,
 };
 use serde_json::json;
 
 use log::*;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
 pub(crate) async fn pod_info(
     client: Arc<Client>,
     namespace: &str,
     pod_name: &str,
-) -> Result<String> {
-    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), &namespace);
-    let pod = pod_api.get(&pod_name).await.map_err(Error::from)?;
+    params: pod::PodInfoParams,
+) -> Result<pod::PodDetail> {
+    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), namespace);
+    let mut pod = pod_api.get(pod_name).await.map_err(Error::from)?;
     info!("Pod {} was found in namespace {}", pod_name, namespace);
-    let pod_spec_json = serde_json::to_string(&pod.spec.unwrap()).map_err(Error::from)?;
-    Ok(pod_spec_json)
+    if params.strip_managed_fields.unwrap_or_default() {
+        pod.metadata.managed_fields = None;
+    }
+    let diagnosis = diagnose(&pod);
+    let events = event_service::for_object(client, namespace, "Pod", pod_name).await;
+    Ok(pod::PodDetail {
+        pod,
+        diagnosis,
+        events,
+    })
+}
+
+/// Explains why a pod is not ready from its conditions and container states.
+fn diagnose(pod: &Pod) -> pod::PodDiagnosis {
+    let mut diagnosis = pod::PodDiagnosis::default();
+    let Some(status) = pod.status.as_ref() else {
+        diagnosis.problems.push(pod::PodProblem {
+            container: None,
+            reason: "NoStatus".to_owned(),
+            message: "The pod has not been processed by the cluster yet".to_owned(),
+        });
+        return diagnosis;
+    };
+    let conditions = status.conditions.as_deref().unwrap_or_default();
+    diagnosis.ready = conditions
+        .iter()
+        .any(|c| c.type_ == "Ready" && c.status == "True");
+
+    if let Some(scheduled) = conditions
+        .iter()
+        .find(|c| c.type_ == "PodScheduled" && c.status == "False")
+    {
+        diagnosis.problems.push(pod::PodProblem {
+            container: None,
+            reason: scheduled
+                .reason
+                .clone()
+                .unwrap_or_else(|| "Unschedulable".to_owned()),
+            message: scheduled.message.clone().unwrap_or_default(),
+        });
+    }
+
+    let containers = status
+        .init_container_statuses
+        .iter()
+        .chain(status.container_statuses.iter())
+        .flatten();
+    for container in containers {
+        if let Some(problem) = diagnose_container(container) {
+            diagnosis.problems.push(problem);
+        }
+    }
+
+    if !diagnosis.ready && diagnosis.problems.is_empty() {
+        if let Some(ready) = conditions.iter().find(|c| c.type_ == "Ready") {
+            diagnosis.problems.push(pod::PodProblem {
+                container: None,
+                reason: ready
+                    .reason
+                    .clone()
+                    .unwrap_or_else(|| "NotReady".to_owned()),
+                message: ready.message.clone().unwrap_or_default(),
+            });
+        }
+    }
+    diagnosis
+}
+
+fn diagnose_container(container: &ContainerStatus) -> Option<pod::PodProblem> {
+    let state = container.state.as_ref();
+    let waiting = state.and_then(|state| state.waiting.as_ref());
+    let terminated = state.and_then(|state| state.terminated.as_ref());
+    let last_terminated = container
+        .last_state
+        .as_ref()
+        .and_then(|state| state.terminated.as_ref());
+    let problem = |reason: &str, message: String| {
+        Some(pod::PodProblem {
+            container: Some(container.name.clone()),
+            reason: reason.to_owned(),
+            message,
+        })
+    };
+
+    let oom_killed = [terminated, last_terminated]
+        .into_iter()
+        .flatten()
+        .any(|t| t.reason.as_deref() == Some("OOMKilled"));
+    match waiting.and_then(|waiting| waiting.reason.as_deref()) {
+        Some("CrashLoopBackOff") => {
+            let last_exit = last_terminated
+                .map(|t| {
+                    format!(
+                        ", last exit code {} ({})",
+                        t.exit_code,
+                        t.reason.as_deref().unwrap_or("Error")
+                    )
+                })
+                .unwrap_or_default();
+            let hint = if oom_killed {
+                ", it was killed for exceeding its memory limit"
+            } else {
+                ", check the logs of the previous instance"
+            };
+            problem(
+                "CrashLoopBackOff",
+                format!(
+                    "Container {} keeps crashing and has restarted {} times{}{}",
+                    container.name, container.restart_count, last_exit, hint
+                ),
+            )
+        }
+        Some(reason @ ("ImagePullBackOff" | "ErrImagePull" | "InvalidImageName")) => problem(
+            reason,
+            format!(
+                "Image {} cannot be pulled: {}",
+                container.image,
+                waiting
+                    .and_then(|waiting| waiting.message.as_deref())
+                    .unwrap_or("check the image name, tag and pull secrets")
+            ),
+        ),
+        Some("ContainerCreating" | "PodInitializing") => None,
+        Some(reason) => problem(
+            reason,
+            waiting
+                .and_then(|waiting| waiting.message.clone())
+                .unwrap_or_default(),
+        ),
+        // a container that recovered from an earlier OOM kill is healthy
+        None if oom_killed && (!container.ready || terminated.is_some()) => problem(
+            "OOMKilled",
+            format!(
+                "Container {} was killed for exceeding its memory limit",
+                container.name
+            ),
+        ),
+        None => match terminated {
+            Some(t) if t.exit_code != 0 => problem(
+                t.reason.as_deref().unwrap_or("Error"),
+                format!(
+                    "Container {} exited with code {}",
+                    container.name, t.exit_code
+                ),
+            ),
+            _ if !container.ready && state.is_some_and(|s| s.running.is_some()) => problem(
+                "NotReady",
+                format!(
+                    "Container {} is running but not ready, check its readiness probe",
+                    container.name
+                ),
+            ),
+            _ => None,
+        },
+    }
 }
 
 pub(crate) async fn pod_logs(
     client: Arc<Client>,
     namespace: &str,
     pod_name: &str,
+    query: &pod::LogQuery,
 ) -> Result<String> {
-    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), &namespace);
-    let logs = pod_api
-        .logs(
-            pod_name,
-            &LogParams {
-                follow: false,
-                since_seconds: Some(10),
-                ..LogParams::default()
-            },
-        )
-        .await?;
+    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), namespace);
+    let logs = pod_api.logs(pod_name, &log_params(query, false)?).await?;
     Ok(logs.to_owned())
 }
 
+/// Follows the log of a pod, yielding it line by line as it is written.
+pub(crate) async fn pod_log_stream(
+    client: Arc<Client>,
+    namespace: &str,
+    pod_name: &str,
+    query: &pod::LogQuery,
+) -> Result<impl Stream<Item = std::io::Result<String>> + Send> {
+    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), namespace);
+    let stream = pod_api
+        .log_stream(pod_name, &log_params(query, true)?)
+        .await?;
+    info!(
+        "Following logs of pod {} in namespace {}",
+        pod_name, namespace
+    );
+    Ok(log_lines(stream))
+}
+
+/// Follows the logs of every container of the pods matching a label
+/// selector, like stern. Pods created later are picked up by a watcher and
+/// containers are followed again once they restart.
+pub(crate) async fn aggregate_logs(
+    client: Arc<Client>,
+    namespace: &str,
+    query: pod::AggregateLogQuery,
+) -> Result<impl Stream<Item = pod::LogLine> + Send> {
+    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), namespace);
+    let labels = query.labels.unwrap_or_default();
+    let lp = ListParams::default().labels(&labels);
+    // fail early on a bad selector instead of inside the watcher task
+    pod_api.list(&lp.clone().limit(1)).await?;
+    info!(
+        "Following logs of pods '{}' in namespace {}",
+        labels, namespace
+    );
+
+    let log_params = LogParams {
+        follow: true,
+        timestamps: true,
+        tail_lines: query.tail_lines,
+        since_seconds: query.since_seconds,
+        ..LogParams::default()
+    };
+    let container_filter = query.container;
+    let (tx, rx) = mpsc::channel(256);
+    let following = Arc::new(Mutex::new(HashSet::new()));
+    tokio::spawn(async move {
+        let config = watcher::Config::default().labels(&labels);
+        let mut pods = watcher(pod_api.clone(), config)
+            .backoff(watcher::default_backoff())
+            .boxed();
+        loop {
+            // stop watching as soon as the client goes away, not on the
+            // next pod event
+            let event = tokio::select! {
+                _ = tx.closed() => break,
+                event = pods.next() => event,
+            };
+            let applied = match event {
+                Some(Ok(watcher::Event::Applied(pod))) => vec![pod],
+                Some(Ok(watcher::Event::Restarted(pods))) => pods,
+                Some(Ok(watcher::Event::Deleted(_))) => continue,
+                Some(Err(e)) => {
+                    warn!("watching pods for logs failed: {}", e);
+                    continue;
+                }
+                None => break,
+            };
+            for pod in applied {
+                let pod_name = pod.metadata.name.clone().unwrap_or_default();
+                for container in running_containers(&pod) {
+                    if matches!(&container_filter, Some(name) if *name != container) {
+                        continue;
+                    }
+                    let key = (pod_name.clone(), container.clone());
+                    if !following.lock().unwrap().insert(key.clone()) {
+                        continue;
+                    }
+                    let log_params = LogParams {
+                        container: Some(container.clone()),
+                        ..log_params.clone()
+                    };
+                    let pod_api = pod_api.clone();
+                    let following = following.clone();
+                    let tx = tx.clone();
+                    tokio::spawn(async move {
+                        follow_container(pod_api, &key.0, &key.1, log_params, &tx).await;
+                        following.lock().unwrap().remove(&key);
+                    });
+                }
+            }
+        }
+    });
+    Ok(stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|line| (line, rx))
+    }))
+}
+
+/// Pause before following a log again whose stream ended early.
+const REFOLLOW_DELAY: Duration = Duration::from_secs(1);
+
+/// Sends the log lines of a container until it stops running or the client
+/// goes away. A log stream can end while the container still runs, e.g. when
+/// the API server times the request out, so it is then followed again from
+/// the last line received.
+async fn follow_container(
+    pod_api: Api<Pod>,
+    pod_name: &str,
+    container: &str,
+    mut log_params: LogParams,
+    tx: &mpsc::Sender<pod::LogLine>,
+) {
+    let mut last_seen = None;
+    loop {
+        let mut lines = match pod_api.log_stream(pod_name, &log_params).await {
+            Ok(stream) => log_lines(stream),
+            Err(e) => {
+                warn!("following logs of {}/{} failed: {}", pod_name, container, e);
+                return;
+            }
+        };
+        loop {
+            let line = tokio::select! {
+                _ = tx.closed() => return,
+                line = lines.next() => line,
+            };
+            let Some(Ok(line)) = line else {
+                break;
+            };
+            let time = line_time(&line);
+            // a log followed again starts on a whole second, skip the lines
+            // of that second that were sent already
+            if time.is_some() && time <= last_seen {
+                continue;
+            }
+            last_seen = time.or(last_seen);
+            let line = pod::LogLine {
+                pod: pod_name.to_owned(),
+                container: container.to_owned(),
+                line,
+            };
+            if tx.send(line).await.is_err() {
+                return;
+            }
+        }
+        if !still_running(&pod_api, pod_name, container).await {
+            return;
+        }
+        debug!(
+            "log stream of {}/{} ended early, following it again",
+            pod_name, container
+        );
+        tokio::time::sleep(REFOLLOW_DELAY).await;
+        if let Some(last_seen) = last_seen {
+            log_params = refollow_params(&log_params, last_seen, Utc::now());
+        }
+    }
+}
+
+/// The timestamp a log line read with `timestamps` starts with.
+fn line_time(line: &str) -> Option<DateTime<Utc>> {
+    let (time, _) = line.split_once(' ')?;
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Parameters reading a log again from the second of the last line seen,
+/// the API only takes whole seconds.
+fn refollow_params(
+    log_params: &LogParams,
+    last_seen: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> LogParams {
+    let elapsed = now.signed_duration_since(last_seen).num_seconds();
+    LogParams {
+        tail_lines: None,
+        since_seconds: Some(elapsed.max(0) + 1),
+        ..log_params.clone()
+    }
+}
+
+/// Whether a container of a pod is still running, so its log can go on.
+async fn still_running(pod_api: &Api<Pod>, pod_name: &str, container: &str) -> bool {
+    match pod_api.get_opt(pod_name).await {
+        Ok(pod) => pod.is_some_and(|pod| running_containers(&pod).iter().any(|c| c == container)),
+        Err(e) => {
+            warn!("checking pod {} failed: {}", pod_name, e);
+            false
+        }
+    }
+}
+
+/// Names of the containers of a pod that have started, only those have logs.
+fn running_containers(pod: &Pod) -> Vec<String> {
+    pod.status
+        .as_ref()
+        .and_then(|status| status.container_statuses.as_ref())
+        .map(|statuses| {
+            statuses
+                .iter()
+                .filter(|status| {
+                    status
+                        .state
+                        .as_ref()
+                        .is_some_and(|state| state.running.is_some())
+                })
+                .map(|status| status.name.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn log_lines(
+    stream: impl Stream<Item = kube::Result<Bytes>> + Send + 'static,
+) -> impl Stream<Item = std::io::Result<String>> + Send {
+    Box::pin(stream)
+        .map_err(std::io::Error::other)
+        .into_async_read()
+        .lines()
+}
+
+fn log_params(query: &pod::LogQuery, follow: bool) -> Result<LogParams> {
+    if query.since_time.is_some() && query.since_seconds.is_some() {
+        // the API server rejects the combination too
+        return Err(Error::BadRequest(
+            "Only one of sinceTime and sinceSeconds may be given".to_owned(),
+        ));
+    }
+    let mut since_seconds = query.since_seconds;
+    if let Some(since_time) = &query.since_time {
+        let since_time = DateTime::parse_from_rfc3339(since_time)
+            .map_err(|e| Error::BadRequest(format!("Invalid sinceTime: {}", e)))?;
+        let elapsed = Utc::now().signed_duration_since(since_time).num_seconds();
+        since_seconds = Some(elapsed.max(1));
+    }
+    Ok(LogParams {
+        container: query.container.clone(),
+        follow,
+        limit_bytes: query.limit_bytes,
+        previous: query.previous.unwrap_or_default(),
+        since_seconds,
+        tail_lines: query.tail_lines,
+        timestamps: query.timestamps.unwrap_or_default(),
+        ..LogParams::default()
+    })
+}
+
+const DEFAULT_EXEC_TIMEOUT: Duration = Duration::from_secs(60);
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
 pub(crate) async fn exec(
     client: Arc<Client>,
     namespace: &str,
     pod_name: &str,
     exec_params: pod::ExecParams,
-) -> String {
-    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), &namespace);
+) -> Result<pod::ExecResult> {
+    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), namespace);
 
     let attach_params = AttachParams {
         container: exec_params.container,
+        stdout: true,
+        stderr: true,
         ..Default::default()
     };
+    let timeout = exec_params
+        .timeout_seconds
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_EXEC_TIMEOUT);
+    let max_output_bytes = exec_params
+        .max_output_bytes
+        .unwrap_or(DEFAULT_MAX_OUTPUT_BYTES);
     info!("exec command {:?} in {}", exec_params.commands, pod_name);
+    let started = Instant::now();
     let attached = pod_api
-        .exec(&pod_name, exec_params.commands, &attach_params)
-        .await
-        .unwrap();
-    let output = get_output(attached).await;
-    info!("{output}");
-    output
+        .exec(pod_name, exec_params.commands, &attach_params)
+        .await?;
+    let result = get_output(attached, timeout, max_output_bytes, started).await?;
+    info!(
+        "exec in {} finished with {:?} after {}ms",
+        pod_name, result.exit_code, result.duration_ms
+    );
+    Ok(result)
 }
 
-async fn get_output(mut attached: AttachedProcess) -> String {
-    let stdout = tokio_util::io::ReaderStream::new(attached.stdout().unwrap());
-    let out = stdout
-        .filter_map(|r| async { r.ok().and_then(|v| String::from_utf8(v.to_vec()).ok()) })
-        .collect::<Vec<_>>()
+async fn get_output(
+    mut attached: AttachedProcess,
+    timeout: Duration,
+    max_output_bytes: usize,
+    started: Instant,
+) -> Result<pod::ExecResult> {
+    let stdout = attached.stdout();
+    let stderr = attached.stderr();
+    let status = attached.take_status();
+    let mut stdout_buf = Vec::new();
+    let mut stderr_buf = Vec::new();
+    let outcome = tokio::time::timeout(timeout, async {
+        let (stdout_truncated, stderr_truncated) = tokio::try_join!(
+            read_capped(stdout, &mut stdout_buf, max_output_bytes),
+            read_capped(stderr, &mut stderr_buf, max_output_bytes),
+        )?;
+        let status = match status {
+            Some(status) => status.await,
+            None => None,
+        };
+        Ok::<_, std::io::Error>((stdout_truncated || stderr_truncated, status))
+    })
+    .await;
+
+    let mut result = pod::ExecResult {
+        duration_ms: started.elapsed().as_millis() as u64,
+        ..Default::default()
+    };
+    match outcome {
+        Ok(Ok((truncated, status))) => {
+            result.truncated = truncated;
+            result.exit_code = status.as_ref().and_then(exit_code);
+            attached
+                .join()
+                .await
+                .map_err(|e| Error::General(e.into()))?;
+        }
+        Ok(Err(e)) => return Err(Error::General(e.into())),
+        Err(_) => {
+            result.timed_out = true;
+            result.truncated =
+                stdout_buf.len() >= max_output_bytes || stderr_buf.len() >= max_output_bytes;
+            attached.abort();
+        }
+    }
+    result.stdout = String::from_utf8_lossy(&stdout_buf).into_owned();
+    result.stderr = String::from_utf8_lossy(&stderr_buf).into_owned();
+    Ok(result)
+}
+
+/// Reads `reader` to the end, keeping at most `cap` bytes in `buf`.
+///
+/// Output past the cap is drained rather than left unread so the command
+/// does not block on a full pipe. Returns whether anything was dropped.
+async fn read_capped(
+    reader: Option<impl AsyncRead + Unpin>,
+    buf: &mut Vec<u8>,
+    cap: usize,
+) -> std::io::Result<bool> {
+    let Some(mut reader) = reader else {
+        return Ok(false);
+    };
+    let mut truncated = false;
+    let mut chunk = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(truncated);
+        }
+        let keep = n.min(cap.saturating_sub(buf.len()));
+        buf.extend_from_slice(&chunk[..keep]);
+        truncated |= keep < n;
+    }
+}
+
+/// Extracts the exit code from the status the API server sends when the
+/// command finishes; failures carry it in an `ExitCode` cause.
+fn exit_code(status: &Status) -> Option<i32> {
+    if status.status.as_deref() == Some("Success") {
+        return Some(0);
+    }
+    status
+        .details
+        .as_ref()?
+        .causes
+        .as_ref()?
+        .iter()
+        .find(|cause| cause.reason.as_deref() == Some("ExitCode"))?
+        .message
+        .as_ref()?
+        .parse()
+        .ok()
+}
+
+/// Streams a tar archive of a file or directory inside a container, as
+/// produced by `tar cf -`. Returns the archive's file name with the stream.
+pub(crate) async fn download(
+    client: Arc<Client>,
+    namespace: &str,
+    pod_name: &str,
+    params: pod::CopyParams,
+) -> Result<(String, impl Stream<Item = std::io::Result<Bytes>> + Send)> {
+    let path = std::path::Path::new(&params.path);
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| Error::BadRequest(format!("Invalid path: {}", params.path)))?;
+    let parent = path
+        .parent()
+        .and_then(|parent| parent.to_str())
+        .filter(|parent| !parent.is_empty())
+        .unwrap_or(".");
+    let exists = run(
+        &client,
+        namespace,
+        pod_name,
+        &params.container,
+        &["test", "-e", &params.path],
+    )
+    .await?;
+    if exists.exit_code != Some(0) {
+        return Err(Error::BadRequest(format!(
+            "{} does not exist in pod {}",
+            params.path, pod_name
+        )));
+    }
+
+    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), namespace);
+    let attach_params = AttachParams {
+        container: params.container,
+        stdout: true,
+        stderr: false,
+        ..Default::default()
+    };
+    info!("download {} from {}", params.path, pod_name);
+    let mut attached = pod_api
+        .exec(
+            pod_name,
+            vec!["tar", "cf", "-", "-C", parent, name],
+            &attach_params,
+        )
+        .await?;
+    let stdout = attached
+        .stdout()
+        .ok_or_else(|| Error::General(anyhow!("stdout is not available")))?;
+    let pod_name = pod_name.to_owned();
+    tokio::spawn(async move {
+        if let Err(e) = attached.join().await {
+            warn!("download from {} failed: {}", pod_name, e);
+        }
+    });
+    Ok((
+        format!("{}.tar", name),
+        tokio_util::io::ReaderStream::new(stdout),
+    ))
+}
+
+/// Writes files into a directory of a container by piping a tar archive
+/// into `tar xf -`. File names may contain `/` to create subdirectories.
+pub(crate) async fn upload(
+    client: Arc<Client>,
+    namespace: &str,
+    pod_name: &str,
+    params: pod::CopyParams,
+    files: Vec<(String, Bytes)>,
+) -> Result<Vec<String>> {
+    let mut archive = tar::Builder::new(Vec::new());
+    let mut uploaded = Vec::new();
+    for (file_name, data) in &files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        archive
+            .append_data(&mut header, file_name, data.as_ref())
+            .map_err(|e| Error::BadRequest(format!("Invalid file {}: {}", file_name, e)))?;
+        uploaded.push(format!(
+            "{}/{}",
+            params.path.trim_end_matches('/'),
+            file_name
+        ));
+    }
+    let archive = archive.into_inner().map_err(|e| Error::General(e.into()))?;
+
+    let mkdir = run(
+        &client,
+        namespace,
+        pod_name,
+        &params.container,
+        &["mkdir", "-p", &params.path],
+    )
+    .await?;
+    if mkdir.exit_code != Some(0) {
+        return Err(Error::General(anyhow!(
+            "Cannot create {} in pod {}: {}",
+            params.path,
+            pod_name,
+            mkdir.stderr
+        )));
+    }
+
+    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), namespace);
+    let attach_params = AttachParams {
+        container: params.container,
+        stdin: true,
+        stdout: false,
+        stderr: true,
+        ..Default::default()
+    };
+    info!(
+        "upload {} files to {} in {}",
+        files.len(),
+        params.path,
+        pod_name
+    );
+    let mut attached = pod_api
+        .exec(
+            pod_name,
+            vec!["tar", "xf", "-", "-C", &params.path],
+            &attach_params,
+        )
+        .await?;
+    let mut stdin = attached
+        .stdin()
+        .ok_or_else(|| Error::General(anyhow!("stdin is not available")))?;
+    stdin
+        .write_all(&archive)
         .await
-        .join("");
-    attached.join().await.unwrap();
-    out
+        .map_err(|e| Error::General(e.into()))?;
+    drop(stdin);
+    let result = get_output(
+        attached,
+        DEFAULT_EXEC_TIMEOUT,
+        DEFAULT_MAX_OUTPUT_BYTES,
+        Instant::now(),
+    )
+    .await?;
+    if result.exit_code != Some(0) {
+        return Err(Error::General(anyhow!(
+            "Extracting into {} failed: {}",
+            params.path,
+            result.stderr
+        )));
+    }
+    Ok(uploaded)
+}
+
+/// Runs a short helper command in a container through `exec`.
+async fn run(
+    client: &Arc<Client>,
+    namespace: &str,
+    pod_name: &str,
+    container: &Option<String>,
+    commands: &[&str],
+) -> Result<pod::ExecResult> {
+    let exec_params = pod::ExecParams {
+        container: container.clone(),
+        commands: commands.iter().map(|c| c.to_string()).collect(),
+        timeout_seconds: None,
+        max_output_bytes: None,
+    };
+    exec(client.clone(), namespace, pod_name, exec_params).await
+}
+
+/// Channel prefixes of binary frames sent to terminal clients, numbered
+/// like the Kubernetes exec channels.
+const STDOUT_CHANNEL: u8 = 1;
+const STDERR_CHANNEL: u8 = 2;
+
+/// Bridges an interactive exec session to a WebSocket until either side closes.
+///
+/// Output is sent as binary frames prefixed with the channel byte, the exit
+/// status is sent as a final `{"type": "exit"}` text frame and a failure as
+/// a final `{"type": "error"}` text frame.
+pub(crate) async fn exec_terminal(
+    client: Arc<Client>,
+    namespace: &str,
+    pod_name: &str,
+    params: pod::TerminalParams,
+    socket: WebSocket,
+) -> Result<()> {
+    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), namespace);
+    let tty = params.tty.unwrap_or(true);
+    let command: Vec<String> = params
+        .command
+        .as_deref()
+        .unwrap_or("/bin/sh")
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+    let attach_params = AttachParams {
+        container: params.container,
+        stdin: true,
+        stdout: true,
+        // stderr is merged into stdout when a TTY is allocated
+        stderr: !tty,
+        tty,
+        ..Default::default()
+    };
+    info!("open terminal {:?} in {}", command, pod_name);
+    match pod_api.exec(pod_name, command, &attach_params).await {
+        Ok(attached) => bridge_terminal(attached, pod_name, socket).await,
+        Err(e) => fail_session(socket, e.into()).await,
+    }
+}
+
+/// Attaches a WebSocket to the main process of a running container, such
+/// as an ephemeral debug container, speaking the same protocol as
+/// [`exec_terminal`].
+pub(crate) async fn attach_terminal(
+    client: Arc<Client>,
+    namespace: &str,
+    pod_name: &str,
+    params: pod::TerminalParams,
+    socket: WebSocket,
+) -> Result<()> {
+    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), namespace);
+    let tty = params.tty.unwrap_or(true);
+    let attach_params = AttachParams {
+        container: params.container,
+        stdin: true,
+        stdout: true,
+        stderr: !tty,
+        tty,
+        ..Default::default()
+    };
+    info!("attach terminal to {}", pod_name);
+    match pod_api.attach(pod_name, &attach_params).await {
+        Ok(attached) => bridge_terminal(attached, pod_name, socket).await,
+        Err(e) => fail_session(socket, e.into()).await,
+    }
+}
+
+async fn bridge_terminal(
+    mut attached: AttachedProcess,
+    pod_name: &str,
+    socket: WebSocket,
+) -> Result<()> {
+    let Some(stdin) = attached.stdin() else {
+        let e = Error::General(anyhow!("stdin is not available"));
+        return fail_session(socket, e).await;
+    };
+    let terminal_size = attached.terminal_size();
+    let status = attached.take_status();
+    let outputs = stream::select_all(
+        [
+            attached.stdout().map(|r| output_frames(r, STDOUT_CHANNEL)),
+            attached.stderr().map(|r| output_frames(r, STDERR_CHANNEL)),
+        ]
+        .into_iter()
+        .flatten(),
+    );
+
+    let (mut sender, mut receiver) = socket.split();
+    let relayed = relay_terminal(
+        outputs,
+        stdin,
+        terminal_size,
+        &mut sender,
+        &mut receiver,
+        pod_name,
+    )
+    .await;
+    if let Err(e) = relayed {
+        // the client may already be gone, nothing left to report to it then
+        let _ = sender.send(error_frame(&e)).await;
+        let _ = sender.close().await;
+        return Err(e);
+    }
+
+    let status = match status {
+        Some(status) => status.await,
+        None => None,
+    };
+    info!("terminal in {} exited with {:?}", pod_name, status);
+    let exit = json!({ "type": "exit", "status": status });
+    // the client may already be gone, nothing left to report to it then
+    let _ = sender.send(Message::Text(exit.to_string())).await;
+    let _ = sender.close().await;
+    Ok(())
+}
+
+/// Copies output to the client and client input to stdin until either side
+/// closes. Stdin is dropped on return, which ends the input of the process.
+async fn relay_terminal(
+    mut outputs: impl Stream<Item = std::io::Result<Message>> + Unpin,
+    mut stdin: impl AsyncWrite + Unpin,
+    mut terminal_size: Option<futures::channel::mpsc::Sender<TerminalSize>>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    receiver: &mut SplitStream<WebSocket>,
+    pod_name: &str,
+) -> Result<()> {
+    loop {
+        tokio::select! {
+            output = outputs.next() => match output {
+                Some(Ok(message)) => sender.send(message).await.map_err(|e| Error::General(e.into()))?,
+                Some(Err(e)) => return Err(Error::General(e.into())),
+                None => break,
+            },
+            message = receiver.next() => match message {
+                Some(Ok(Message::Binary(data))) => {
+                    stdin.write_all(&data).await.map_err(|e| Error::General(e.into()))?;
+                }
+                Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+                    Ok(pod::TerminalMessage::Stdin { data }) => {
+                        stdin.write_all(data.as_bytes()).await.map_err(|e| Error::General(e.into()))?;
+                    }
+                    Ok(pod::TerminalMessage::Resize { cols, rows }) => {
+                        if let Some(terminal_size) = terminal_size.as_mut() {
+                            let size = TerminalSize { width: cols, height: rows };
+                            terminal_size.send(size).await.map_err(|e| Error::General(e.into()))?;
+                        }
+                    }
+                    // a bad frame must not end the whole session
+                    Err(e) => warn!("skipping terminal frame to {} {:?}: {}", pod_name, text, e),
+                },
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(Error::General(e.into())),
+            },
+        }
+    }
+    Ok(())
 }
 
-pub(crate) async fn find_pod_by_labels(
+/// The `{"type": "error"}` text frame that tells a WebSocket client why its
+/// session ended.
+fn error_frame(err: &Error) -> Message {
+    let error = json!({ "type": "error", "message": err.to_string() });
+    Message::Text(error.to_string())
+}
+
+/// Closes a WebSocket session that failed before it got going, sending the
+/// error to the client first.
+async fn fail_session(mut socket: WebSocket, err: Error) -> Result<()> {
+    // the client may already be gone, nothing left to report to it then
+    let _ = socket.send(error_frame(&err)).await;
+    let _ = socket.close().await;
+    Err(err)
+}
+
+fn output_frames(
+    reader: impl AsyncRead + Send + Unpin + 'static,
+    channel: u8,
+) -> BoxStream<'static, std::io::Result<Message>> {
+    tokio_util::io::ReaderStream::new(reader)
+        .map_ok(move |chunk| {
+            let mut frame = Vec::with_capacity(chunk.len() + 1);
+            frame.push(channel);
+            frame.extend_from_slice(&chunk);
+            Message::Binary(frame)
+        })
+        .boxed()
+}
+
+const DEFAULT_DEBUG_IMAGE: &str = "busybox:1.36";
+const DEFAULT_DEBUG_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Injects an ephemeral debug container into a running pod through the
+/// `ephemeralcontainers` subresource and waits for it to start.
+pub(crate) async fn debug(
     client: Arc<Client>,
     namespace: &str,
+    pod_name: &str,
+    params: pod::DebugParams,
+) -> Result<pod::DebugSession> {
+    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), namespace);
+    let container = params
+        .name
+        .unwrap_or_else(|| format!("debugger-{:x}", Utc::now().timestamp_millis() & 0xfffff));
+    let image = params
+        .image
+        .unwrap_or_else(|| DEFAULT_DEBUG_IMAGE.to_owned());
+    let command = params.command.unwrap_or_else(|| vec!["sh".to_owned()]);
+    let patch = json!({
+        "spec": {
+            "ephemeralContainers": [{
+                "name": container,
+                "image": image,
+                "command": command,
+                "stdin": true,
+                "tty": true,
+                "targetContainerName": params.target,
+            }]
+        }
+    });
+    pod_api
+        .patch_subresource(
+            "ephemeralcontainers",
+            pod_name,
+            &PatchParams::default(),
+            &Patch::Strategic(&patch),
+        )
+        .await?;
+    info!(
+        "added debug container {} ({}) to pod {}",
+        container, image, pod_name
+    );
+
+    let timeout = params
+        .timeout_seconds
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_DEBUG_TIMEOUT);
+    let name = container.clone();
+    let started = await_condition(pod_api.clone(), pod_name, move |pod: Option<&Pod>| {
+        ephemeral_state(pod, &name).is_some_and(|state| state.running.is_some())
+    });
+    if tokio::time::timeout(timeout, started).await.is_err() {
+        let pod = pod_api.get(pod_name).await?;
+        let reason = ephemeral_state(Some(&pod), &container)
+            .and_then(|state| state.waiting.as_ref())
+            .and_then(|waiting| waiting.reason.clone())
+            .unwrap_or_else(|| "not started".to_owned());
+        return Err(Error::General(anyhow!(
+            "Debug container {} did not start within {:?}: {}",
+            container,
+            timeout,
+            reason
+        )));
+    }
+
+    let pod_path = format!("/api/v1/namespaces/{}/pods/{}", namespace, pod_name);
+    Ok(pod::DebugSession {
+        attach: format!("{}/attach?container={}", pod_path, container),
+        exec: format!("{}/exec?container={}", pod_path, container),
+        pod: pod_name.to_owned(),
+        container,
+        image,
+    })
+}
+
+fn ephemeral_state<'a>(pod: Option<&'a Pod>, container: &str) -> Option<&'a ContainerState> {
+    pod?.status
+        .as_ref()?
+        .ephemeral_container_statuses
+        .as_ref()?
+        .iter()
+        .find(|status| status.name == container)?
+        .state
+        .as_ref()
+}
+
+/// Tunnels a pod port over a WebSocket, bytes are carried in binary frames.
+/// A failure is sent as a final `{"type": "error"}` text frame, like the
+/// terminal sessions do.
+pub(crate) async fn port_forward(
+    client: Arc<Client>,
+    namespace: &str,
+    pod_name: &str,
+    port: u16,
+    socket: WebSocket,
+) -> Result<()> {
+    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), namespace);
+    let mut forwarder = match pod_api.portforward(pod_name, &[port]).await {
+        Ok(forwarder) => forwarder,
+        Err(e) => return fail_session(socket, e.into()).await,
+    };
+    let Some(upstream) = forwarder.take_stream(port) else {
+        let e = Error::General(anyhow!("port {} is not forwarded", port));
+        return fail_session(socket, e).await;
+    };
+    info!("forwarding port {} of {} over websocket", port, pod_name);
+    let (upstream_reader, mut upstream_writer) = tokio::io::split(upstream);
+    let mut upstream_chunks = tokio_util::io::ReaderStream::new(upstream_reader);
+
+    let (mut sender, mut receiver) = socket.split();
+    let result = loop {
+        tokio::select! {
+            chunk = upstream_chunks.next() => match chunk {
+                Some(Ok(chunk)) => {
+                    if let Err(e) = sender.send(Message::Binary(chunk.to_vec())).await {
+                        break Err(Error::General(e.into()));
+                    }
+                }
+                Some(Err(e)) => break Err(Error::General(e.into())),
+                None => break Ok(()),
+            },
+            message = receiver.next() => match message {
+                Some(Ok(Message::Binary(data))) => {
+                    if let Err(e) = upstream_writer.write_all(&data).await {
+                        break Err(Error::General(e.into()));
+                    }
+                }
+                Some(Ok(Message::Text(text))) => {
+                    if let Err(e) = upstream_writer.write_all(text.as_bytes()).await {
+                        break Err(Error::General(e.into()));
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None => break Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => break Err(Error::General(e.into())),
+            },
+        }
+    };
+    forwarder.abort();
+    if let Err(e) = &result {
+        // the client may already be gone, nothing left to report to it then
+        let _ = sender.send(error_frame(e)).await;
+    }
+    let _ = sender.close().await;
+    info!("stopped forwarding port {} of {}", port, pod_name);
+    result
+}
+
+const DEFAULT_PORT_FORWARD_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// A port-forward listener that is still open, by id
+struct OpenListener {
+    namespace: String,
+    listener: pod::PortForwardListener,
+    stop: CancellationToken,
+}
+
+static PORT_FORWARD_LISTENERS: Mutex<BTreeMap<String, OpenListener>> = Mutex::new(BTreeMap::new());
+static NEXT_PORT_FORWARD_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Binds a local TCP listener and forwards every accepted connection to a
+/// pod port until the listener is closed or its TTL runs out. Only loopback
+/// addresses are bound, the tunnel is not authenticated.
+pub(crate) async fn port_forward_listener(
+    client: Arc<Client>,
+    namespace: &str,
+    pod_name: &str,
+    port: u16,
+    params: pod::PortForwardParams,
+) -> Result<pod::PortForwardListener> {
+    let ip = params.address.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    if !ip.is_loopback() {
+        return Err(Error::BadRequest(format!(
+            "Port-forward listeners only bind loopback addresses, not {}",
+            ip
+        )));
+    }
+    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), namespace);
+    pod_api.get(pod_name).await?;
+
+    let address = SocketAddr::new(ip, params.local_port.unwrap_or(0));
+    let listener = TcpListener::bind(address)
+        .await
+        .map_err(|e| Error::General(e.into()))?;
+    let local_address = listener
+        .local_addr()
+        .map_err(|e| Error::General(e.into()))?;
+    let ttl = params
+        .ttl_seconds
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_PORT_FORWARD_TTL);
+    let id = format!(
+        "pf-{}",
+        NEXT_PORT_FORWARD_ID.fetch_add(1, Ordering::Relaxed)
+    );
+    info!(
+        "forwarding {} to port {} of {} for {:?} as {}",
+        local_address, port, pod_name, ttl, id
+    );
+
+    let info = pod::PortForwardListener {
+        id: id.clone(),
+        pod: pod_name.to_owned(),
+        address: local_address.to_string(),
+        pod_port: port,
+        ttl_seconds: ttl.as_secs(),
+    };
+    let stop = CancellationToken::new();
+    PORT_FORWARD_LISTENERS.lock().unwrap().insert(
+        id.clone(),
+        OpenListener {
+            namespace: namespace.to_owned(),
+            listener: info.clone(),
+            stop: stop.clone(),
+        },
+    );
+
+    let pod_name = pod_name.to_owned();
+    tokio::spawn(async move {
+        let accept = async {
+            loop {
+                let accepted = tokio::select! {
+                    _ = stop.cancelled() => return,
+                    accepted = listener.accept() => accepted,
+                };
+                let (connection, peer) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        error!("port-forward listener {} failed: {}", local_address, e);
+                        return;
+                    }
+                };
+                let pod_api = pod_api.clone();
+                let pod_name = pod_name.clone();
+                let stop = stop.child_token();
+                tokio::spawn(async move {
+                    tokio::select! {
+                        _ = stop.cancelled() => {}
+                        result = forward_connection(&pod_api, &pod_name, port, connection) => {
+                            if let Err(e) = result {
+                                warn!("port-forward from {} to {} failed: {}", peer, pod_name, e);
+                            }
+                        }
+                    }
+                });
+            }
+        };
+        let _ = tokio::time::timeout(ttl, accept).await;
+        // drops the connections still open along with the listener
+        stop.cancel();
+        PORT_FORWARD_LISTENERS.lock().unwrap().remove(&id);
+        info!("port-forward listener {} closed", local_address);
+    });
+
+    Ok(info)
+}
+
+/// The port-forward listeners still open in a namespace.
+pub(crate) fn port_forward_listeners(namespace: &str) -> Vec<pod::PortForwardListener> {
+    PORT_FORWARD_LISTENERS
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|open| open.namespace == namespace)
+        .map(|open| open.listener.clone())
+        .collect()
+}
+
+/// Closes a port-forward listener and the connections it accepted.
+pub(crate) fn close_port_forward_listener(
+    namespace: &str,
+    id: &str,
+) -> Result<pod::PortForwardListener> {
+    let mut listeners = PORT_FORWARD_LISTENERS.lock().unwrap();
+    match listeners.get(id) {
+        Some(open) if open.namespace == namespace => {}
+        _ => {
+            return Err(Error::ResourceNotFound(format!(
+                "Port-forward listener {} not found",
+                id
+            )))
+        }
+    }
+    let open = listeners.remove(id).unwrap();
+    open.stop.cancel();
+    info!("closing port-forward listener {}", id);
+    Ok(open.listener)
+}
+
+async fn forward_connection(
+    pod_api: &Api<Pod>,
+    pod_name: &str,
+    port: u16,
+    mut connection: TcpStream,
+) -> Result<()> {
+    let mut forwarder = pod_api.portforward(pod_name, &[port]).await?;
+    let mut upstream = forwarder
+        .take_stream(port)
+        .ok_or_else(|| Error::General(anyhow!("port {} is not forwarded", port)))?;
+    tokio::io::copy_bidirectional(&mut connection, &mut upstream)
+        .await
+        .map_err(|e| Error::General(e.into()))?;
+    drop(upstream);
+    forwarder
+        .join()
+        .await
+        .map_err(|e| Error::General(e.into()))?;
+    Ok(())
+}
+
+/// Lists pods of a namespace, or of all namespaces when `namespace` is
+/// `None`, filtered by label and field selectors.
+pub(crate) async fn list_pods(
+    client: Arc<Client>,
+    namespace: Option<&str>,
     labels: &str,
-) -> Result<String> {
-    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), &namespace);
-    let lp = ListParams::default().labels(&labels); // Filter pods by label
+    fields: &str,
+) -> Result<Vec<pod::PodSummary>> {
+    let pod_api: Api<Pod> = match namespace {
+        Some(namespace) => Api::namespaced(client.as_ref().clone(), namespace),
+        None => Api::all(client.as_ref().clone()),
+    };
+    let lp = ListParams::default().labels(labels).fields(fields);
 
     let pods = pod_api.list(&lp).await.map_err(Error::from)?;
-    let pods_json = pods
+    Ok(pods.iter().map(pod_summary).collect())
+}
+
+fn pod_summary(pod: &Pod) -> pod::PodSummary {
+    let spec = pod.spec.as_ref();
+    let status = pod.status.as_ref();
+    let statuses = status
+        .and_then(|status| status.container_statuses.clone())
+        .unwrap_or_default();
+    let containers: Vec<pod::ContainerSummary> = statuses
         .iter()
-        .map(|pod| {
-            let pod_name = &pod.metadata.name;
-            let pod_namespace = &pod.metadata.namespace;
-            let pod_labels = &pod.metadata.labels;
-
-            json!({
-                "name": pod_name,
-                "namespace": pod_namespace,
-                "labels": pod_labels,
-            })
+        .map(|container| pod::ContainerSummary {
+            name: container.name.clone(),
+            image: container.image.clone(),
+            ready: container.ready,
+            restart_count: container.restart_count,
+            state: container_state(container.state.as_ref()),
         })
-        .collect::<Vec<_>>();
+        .collect();
+    let total = spec.map_or(statuses.len(), |spec| spec.containers.len());
+    let ready = containers.iter().filter(|c| c.ready).count();
+
+    pod::PodSummary {
+        name: pod.metadata.name.clone(),
+        namespace: pod.metadata.namespace.clone(),
+        labels: pod.metadata.labels.clone(),
+        phase: status.and_then(|status| status.phase.clone()),
+        status: pod_status_reason(pod),
+        ready: format!("{}/{}", ready, total),
+        restarts: containers.iter().map(|c| c.restart_count).sum(),
+        containers,
+        node: spec.and_then(|spec| spec.node_name.clone()),
+        pod_ip: status.and_then(|status| status.pod_ip.clone()),
+        qos_class: status.and_then(|status| status.qos_class.clone()),
+        owner: pod
+            .metadata
+            .owner_references
+            .as_ref()
+            .and_then(|owners| {
+                owners
+                    .iter()
+                    .find(|owner| owner.controller == Some(true))
+                    .or_else(|| owners.first())
+            })
+            .map(|owner| pod::OwnerSummary {
+                kind: owner.kind.clone(),
+                name: owner.name.clone(),
+            }),
+        created_at: pod
+            .metadata
+            .creation_timestamp
+            .as_ref()
+            .map(|time| time.0.to_rfc3339()),
+        age: pod
+            .metadata
+            .creation_timestamp
+            .as_ref()
+            .map(|time| human_age(time.0)),
+    }
+}
 
-    let result = json!(pods_json);
-    Ok(result.to_string())
+/// The STATUS column of `kubectl get pods`: the first waiting or terminated
+/// container reason wins over the pod phase.
+fn pod_status_reason(pod: &Pod) -> String {
+    if pod.metadata.deletion_timestamp.is_some() {
+        return "Terminating".to_owned();
+    }
+    let status = pod.status.as_ref();
+    let container_reason = status
+        .and_then(|status| status.container_statuses.as_ref())
+        .and_then(|statuses| {
+            statuses.iter().find_map(|container| {
+                let state = container.state.as_ref()?;
+                state
+                    .waiting
+                    .as_ref()
+                    .and_then(|waiting| waiting.reason.clone())
+                    .or_else(|| {
+                        state
+                            .terminated
+                            .as_ref()
+                            .and_then(|terminated| terminated.reason.clone())
+                    })
+            })
+        });
+    container_reason
+        .or_else(|| status.and_then(|status| status.reason.clone()))
+        .or_else(|| status.and_then(|status| status.phase.clone()))
+        .unwrap_or_else(|| "Unknown".to_owned())
+}
+
+fn container_state(state: Option<&ContainerState>) -> String {
+    let Some(state) = state else {
+        return "Unknown".to_owned();
+    };
+    if state.running.is_some() {
+        "Running".to_owned()
+    } else if let Some(waiting) = &state.waiting {
+        format!("Waiting: {}", waiting.reason.as_deref().unwrap_or_default())
+    } else if let Some(terminated) = &state.terminated {
+        format!(
+            "Terminated: {}",
+            terminated.reason.as_deref().unwrap_or_default()
+        )
+    } else {
+        "Unknown".to_owned()
+    }
+}
+
+/// Formats the time since `created` the way kubectl prints ages.
+pub(crate) fn human_age(created: DateTime<Utc>) -> String {
+    let seconds = Utc::now()
+        .signed_duration_since(created)
+        .num_seconds()
+        .max(0);
+    match seconds {
+        s if s < 120 => format!("{}s", s),
+        s if s < 3 * 3600 => format!("{}m", s / 60),
+        s if s < 2 * 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
 }
 
 pub(crate) async fn del_pod(
@@ -109,9 +1400,321 @@ pub(crate) async fn del_pod(
     namespace: &str,
     pod_name: &str,
 ) -> Result<String> {
-    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), &namespace);
+    let pod_api: Api<Pod> = Api::namespaced(client.as_ref().clone(), namespace);
     let delete_params = DeleteParams::default();
     let delete_result = pod_api.delete(pod_name, &delete_params).await?;
     // delete_params
     Ok(format!("Pod delete result: {:?}", delete_result))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_params_pass_the_query_through() {
+        let query = pod::LogQuery {
+            container: Some("app".to_owned()),
+            tail_lines: Some(10),
+            since_seconds: Some(60),
+            timestamps: Some(true),
+            ..pod::LogQuery::default()
+        };
+        let lp = log_params(&query, true).unwrap();
+        assert!(lp.follow);
+        assert_eq!(lp.container.as_deref(), Some("app"));
+        assert_eq!(lp.tail_lines, Some(10));
+        assert_eq!(lp.since_seconds, Some(60));
+        assert!(lp.timestamps);
+        assert!(!lp.previous);
+    }
+
+    #[test]
+    fn since_time_becomes_since_seconds() {
+        let since_time = Utc::now() - k8s_openapi::chrono::Duration::seconds(90);
+        let query = pod::LogQuery {
+            since_time: Some(since_time.to_rfc3339()),
+            ..pod::LogQuery::default()
+        };
+        let since_seconds = log_params(&query, false).unwrap().since_seconds.unwrap();
+        assert!((90..=91).contains(&since_seconds));
+
+        // the API server wants a positive number of seconds
+        let query = pod::LogQuery {
+            since_time: Some((Utc::now() + k8s_openapi::chrono::Duration::hours(1)).to_rfc3339()),
+            ..pod::LogQuery::default()
+        };
+        assert_eq!(log_params(&query, false).unwrap().since_seconds, Some(1));
+    }
+
+    #[test]
+    fn log_lines_are_timed_by_their_prefix() {
+        let time = line_time("2023-06-01T12:00:00.123456789Z listening on :8080").unwrap();
+        assert_eq!(time.timestamp_nanos(), 1_685_620_800_123_456_789);
+        assert_eq!(line_time("listening on :8080"), None);
+        assert_eq!(line_time(""), None);
+    }
+
+    #[test]
+    fn refollowed_logs_start_at_the_second_of_the_last_line() {
+        let log_params = LogParams {
+            container: Some("app".to_owned()),
+            follow: true,
+            tail_lines: Some(100),
+            since_seconds: Some(3600),
+            ..LogParams::default()
+        };
+        let last_seen = line_time("2023-06-01T12:00:00.700Z done").unwrap();
+        let now = line_time("2023-06-01T12:00:05.200Z now").unwrap();
+        let refollow = refollow_params(&log_params, last_seen, now);
+        assert_eq!(refollow.since_seconds, Some(5));
+        assert_eq!(refollow.tail_lines, None);
+        assert_eq!(refollow.container.as_deref(), Some("app"));
+        assert!(refollow.follow);
+    }
+
+    #[test]
+    fn since_time_and_since_seconds_together_are_a_bad_request() {
+        let query = pod::LogQuery {
+            since_seconds: Some(60),
+            since_time: Some(Utc::now().to_rfc3339()),
+            ..pod::LogQuery::default()
+        };
+        assert!(matches!(
+            log_params(&query, false),
+            Err(Error::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_since_time_is_a_bad_request() {
+        let query = pod::LogQuery {
+            since_time: Some("yesterday".to_owned()),
+            ..pod::LogQuery::default()
+        };
+        assert!(matches!(
+            log_params(&query, false),
+            Err(Error::BadRequest(_))
+        ));
+    }
+
+    fn status(value: serde_json::Value) -> Status {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn exit_code_of_a_successful_command_is_zero() {
+        assert_eq!(exit_code(&status(json!({ "status": "Success" }))), Some(0));
+    }
+
+    #[test]
+    fn exit_code_comes_from_the_exit_code_cause() {
+        let failure = status(json!({
+            "status": "Failure",
+            "reason": "NonZeroExitCode",
+            "details": {
+                "causes": [
+                    { "reason": "Other", "message": "ignored" },
+                    { "reason": "ExitCode", "message": "137" },
+                ],
+            },
+        }));
+        assert_eq!(exit_code(&failure), Some(137));
+    }
+
+    #[test]
+    fn exit_code_is_unknown_without_a_numeric_cause() {
+        assert_eq!(exit_code(&status(json!({ "status": "Failure" }))), None);
+        let garbled = status(json!({
+            "status": "Failure",
+            "details": { "causes": [{ "reason": "ExitCode", "message": "two" }] },
+        }));
+        assert_eq!(exit_code(&garbled), None);
+    }
+
+    #[test]
+    fn error_frames_carry_the_message() {
+        let frame = error_frame(&Error::BadRequest("container app not found".to_owned()));
+        let Message::Text(text) = frame else {
+            panic!("error frames are text frames");
+        };
+        let frame: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            frame,
+            json!({ "type": "error", "message": "container app not found" })
+        );
+    }
+
+    fn pod(value: serde_json::Value) -> Pod {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn container(state: serde_json::Value) -> serde_json::Value {
+        json!({
+            "name": "app",
+            "image": "nginx",
+            "imageID": "",
+            "ready": false,
+            "restartCount": 0,
+            "state": state,
+        })
+    }
+
+    #[test]
+    fn deleted_pods_are_terminating() {
+        let pod = pod(json!({
+            "metadata": { "name": "web", "deletionTimestamp": "2023-01-01T00:00:00Z" },
+            "status": { "phase": "Running" },
+        }));
+        assert_eq!(pod_status_reason(&pod), "Terminating");
+    }
+
+    #[test]
+    fn container_reasons_win_over_the_phase() {
+        let waiting = pod(json!({
+            "metadata": { "name": "web" },
+            "status": {
+                "phase": "Running",
+                "containerStatuses": [container(json!({ "waiting": { "reason": "CrashLoopBackOff" } }))],
+            },
+        }));
+        assert_eq!(pod_status_reason(&waiting), "CrashLoopBackOff");
+
+        let terminated = pod(json!({
+            "metadata": { "name": "job" },
+            "status": {
+                "phase": "Succeeded",
+                "containerStatuses": [container(json!({ "terminated": { "exitCode": 0, "reason": "Completed" } }))],
+            },
+        }));
+        assert_eq!(pod_status_reason(&terminated), "Completed");
+    }
+
+    #[test]
+    fn pod_reason_and_phase_are_fallbacks() {
+        let evicted = pod(json!({
+            "metadata": { "name": "web" },
+            "status": { "phase": "Failed", "reason": "Evicted" },
+        }));
+        assert_eq!(pod_status_reason(&evicted), "Evicted");
+
+        let running = pod(json!({
+            "metadata": { "name": "web" },
+            "status": {
+                "phase": "Running",
+                "containerStatuses": [container(json!({ "running": {} }))],
+            },
+        }));
+        assert_eq!(pod_status_reason(&running), "Running");
+
+        let unknown = pod(json!({ "metadata": { "name": "web" } }));
+        assert_eq!(pod_status_reason(&unknown), "Unknown");
+    }
+
+    fn diagnosed(status: serde_json::Value) -> pod::PodDiagnosis {
+        diagnose(&pod(
+            json!({ "metadata": { "name": "web" }, "status": status }),
+        ))
+    }
+
+    fn crash_looping(last_reason: &str, exit_code: i32) -> serde_json::Value {
+        let mut container = container(json!({ "waiting": { "reason": "CrashLoopBackOff" } }));
+        container["restartCount"] = json!(4);
+        container["lastState"] = json!({
+            "terminated": { "exitCode": exit_code, "reason": last_reason },
+        });
+        container
+    }
+
+    #[test]
+    fn crash_loops_point_at_the_last_exit() {
+        let diagnosis = diagnosed(json!({
+            "phase": "Running",
+            "containerStatuses": [crash_looping("Error", 1)],
+        }));
+        assert!(!diagnosis.ready);
+        assert_eq!(diagnosis.problems.len(), 1);
+        let problem = &diagnosis.problems[0];
+        assert_eq!(problem.container.as_deref(), Some("app"));
+        assert_eq!(problem.reason, "CrashLoopBackOff");
+        assert!(problem.message.contains("restarted 4 times"));
+        assert!(problem.message.contains("last exit code 1 (Error)"));
+        assert!(problem.message.contains("check the logs"));
+    }
+
+    #[test]
+    fn crash_loops_after_an_oom_kill_hint_at_the_memory_limit() {
+        let diagnosis = diagnosed(json!({
+            "phase": "Running",
+            "containerStatuses": [crash_looping("OOMKilled", 137)],
+        }));
+        assert_eq!(diagnosis.problems.len(), 1);
+        let problem = &diagnosis.problems[0];
+        assert_eq!(problem.reason, "CrashLoopBackOff");
+        assert!(problem.message.contains("last exit code 137 (OOMKilled)"));
+        assert!(problem.message.contains("exceeding its memory limit"));
+    }
+
+    #[test]
+    fn image_pull_failures_name_the_image() {
+        let diagnosis = diagnosed(json!({
+            "phase": "Pending",
+            "containerStatuses": [container(json!({
+                "waiting": { "reason": "ImagePullBackOff", "message": "Back-off pulling image" },
+            }))],
+        }));
+        assert_eq!(diagnosis.problems.len(), 1);
+        assert_eq!(diagnosis.problems[0].reason, "ImagePullBackOff");
+        assert_eq!(
+            diagnosis.problems[0].message,
+            "Image nginx cannot be pulled: Back-off pulling image"
+        );
+    }
+
+    #[test]
+    fn unschedulable_pods_report_the_scheduler_message() {
+        let diagnosis = diagnosed(json!({
+            "phase": "Pending",
+            "conditions": [{
+                "type": "PodScheduled",
+                "status": "False",
+                "reason": "Unschedulable",
+                "message": "0/3 nodes are available: 3 Insufficient cpu.",
+            }],
+        }));
+        assert!(!diagnosis.ready);
+        assert_eq!(diagnosis.problems.len(), 1);
+        let problem = &diagnosis.problems[0];
+        assert_eq!(problem.container, None);
+        assert_eq!(problem.reason, "Unschedulable");
+        assert_eq!(
+            problem.message,
+            "0/3 nodes are available: 3 Insufficient cpu."
+        );
+    }
+
+    #[test]
+    fn running_containers_killed_for_memory_are_reported() {
+        let mut container = container(json!({ "running": {} }));
+        container["lastState"] =
+            json!({ "terminated": { "exitCode": 137, "reason": "OOMKilled" } });
+        let diagnosis = diagnosed(json!({ "phase": "Running", "containerStatuses": [container] }));
+        assert_eq!(diagnosis.problems.len(), 1);
+        assert_eq!(diagnosis.problems[0].reason, "OOMKilled");
+    }
+
+    #[test]
+    fn recovered_oom_kills_are_not_reported() {
+        let mut container = container(json!({ "running": {} }));
+        container["ready"] = json!(true);
+        container["lastState"] =
+            json!({ "terminated": { "exitCode": 137, "reason": "OOMKilled" } });
+        let diagnosis = diagnosed(json!({
+            "phase": "Running",
+            "conditions": [{ "type": "Ready", "status": "True" }],
+            "containerStatuses": [container],
+        }));
+        assert!(diagnosis.ready);
+        assert!(diagnosis.problems.is_empty());
+    }
+}
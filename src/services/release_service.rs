@@ -0,0 +1,494 @@
+use crate::models::manifest::{DeleteManifestParams, DeleteResult, ManifestParams};
+use crate::models::release::{
+    InstallParams, Release, ReleaseReport, ReleaseRollbackParams, ReleaseSummary, UpgradeParams,
+};
+use crate::services::{deployment_service, template_service};
+use crate::{Error, Result};
+use anyhow::anyhow;
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::chrono::{SecondsFormat, Utc};
+use k8s_openapi::ByteString;
+use kube::api::{Api, DeleteParams, ListParams, Patch, PatchParams, PostParams};
+use kube::Client;
+use log::*;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+/// Every revision of a release is kept in its own Secret, named
+/// `kube-sphere.release.v1.<release>.v<revision>` and labelled with these.
+const OWNER_LABEL: &str = "owner";
+const NAME_LABEL: &str = "name";
+const REVISION_LABEL: &str = "version";
+const STATUS_LABEL: &str = "status";
+const OWNER: &str = "kube-sphere";
+const RELEASE_KEY: &str = "release";
+
+const PENDING: &str = "pending";
+const DEPLOYED: &str = "deployed";
+const SUPERSEDED: &str = "superseded";
+const FAILED: &str = "failed";
+
+/// Lists the current revision of every release in a namespace.
+pub async fn list(client: Arc<Client>, namespace: &str) -> Result<Vec<ReleaseSummary>> {
+    let secret_api: Api<Secret> = Api::namespaced(client.as_ref().clone(), namespace);
+    let lp = ListParams::default().labels(&format!("{}={}", OWNER_LABEL, OWNER));
+    let mut releases: BTreeMap<String, Vec<Release>> = BTreeMap::new();
+    for secret in secret_api.list(&lp).await? {
+        let release = decode(&secret)?;
+        releases
+            .entry(release.name.clone())
+            .or_default()
+            .push(release);
+    }
+    info!(
+        "Found {} releases in namespace {}",
+        releases.len(),
+        namespace
+    );
+    Ok(releases
+        .values_mut()
+        .filter_map(|revisions| {
+            revisions.sort_by_key(|release| release.revision);
+            current(revisions).map(summary)
+        })
+        .collect())
+}
+
+/// The current revision of a release, with its values and manifest.
+pub async fn get(client: Arc<Client>, namespace: &str, name: &str) -> Result<Release> {
+    let secret_api: Api<Secret> = Api::namespaced(client.as_ref().clone(), namespace);
+    let revisions = revisions(&secret_api, name).await?;
+    current(&revisions).cloned().ok_or_else(|| not_found(name))
+}
+
+/// Every recorded revision of a release, oldest first.
+pub async fn history(
+    client: Arc<Client>,
+    namespace: &str,
+    name: &str,
+) -> Result<Vec<ReleaseSummary>> {
+    let secret_api: Api<Secret> = Api::namespaced(client.as_ref().clone(), namespace);
+    let revisions = revisions(&secret_api, name).await?;
+    if revisions.is_empty() {
+        return Err(not_found(name));
+    }
+    Ok(revisions.iter().map(summary).collect())
+}
+
+/// Renders a template and applies it as revision 1 of a new release.
+pub async fn install(
+    client: Arc<Client>,
+    namespace: &str,
+    params: InstallParams,
+) -> Result<ReleaseReport> {
+    if params.name.is_empty() {
+        return Err(Error::BadRequest("Missing release name".to_owned()));
+    }
+    let secret_api: Api<Secret> = Api::namespaced(client.as_ref().clone(), namespace);
+    if !revisions(&secret_api, &params.name).await?.is_empty() {
+        return Err(Error::ResourceAlreadyExists(anyhow!(
+            "Release {} already exists",
+            params.name
+        )));
+    }
+    let manifest = template_service::render(&params.template, &params.values)?;
+    let release = Release {
+        name: params.name,
+        namespace: namespace.to_owned(),
+        revision: 1,
+        template: params.template,
+        values: params.values,
+        manifest,
+        status: DEPLOYED.to_owned(),
+        updated: now(),
+    };
+    deploy(client, &secret_api, None, release).await
+}
+
+/// Renders the release again with new values or another template, applies
+/// the result and prunes the objects the new manifest no longer has.
+pub async fn upgrade(
+    client: Arc<Client>,
+    namespace: &str,
+    name: &str,
+    params: UpgradeParams,
+) -> Result<ReleaseReport> {
+    let secret_api: Api<Secret> = Api::namespaced(client.as_ref().clone(), namespace);
+    let revisions = revisions(&secret_api, name).await?;
+    let previous = current(&revisions).ok_or_else(|| not_found(name))?;
+    let template = params.template.unwrap_or_else(|| previous.template.clone());
+    let values = params.values.unwrap_or_else(|| previous.values.clone());
+    let manifest = template_service::render(&template, &values)?;
+    let release = Release {
+        name: name.to_owned(),
+        namespace: namespace.to_owned(),
+        revision: next_revision(&revisions),
+        template,
+        values,
+        manifest,
+        status: DEPLOYED.to_owned(),
+        updated: now(),
+    };
+    deploy(client, &secret_api, Some(previous), release).await
+}
+
+/// Applies the manifest of an earlier revision again, recorded as a new
+/// revision like any upgrade. Without a revision the newest one before the
+/// current that did not fail is restored.
+pub async fn rollback(
+    client: Arc<Client>,
+    namespace: &str,
+    name: &str,
+    params: ReleaseRollbackParams,
+) -> Result<ReleaseReport> {
+    let secret_api: Api<Secret> = Api::namespaced(client.as_ref().clone(), namespace);
+    let revisions = revisions(&secret_api, name).await?;
+    let previous = current(&revisions).ok_or_else(|| not_found(name))?;
+    let target = match params.revision {
+        Some(revision) => revisions
+            .iter()
+            .find(|release| release.revision == revision)
+            .ok_or_else(|| {
+                Error::BadRequest(format!("Release {} has no revision {}", name, revision))
+            })?,
+        None => revisions
+            .iter()
+            .rev()
+            .find(|release| {
+                release.revision < previous.revision
+                    && release.status != FAILED
+                    && release.status != PENDING
+            })
+            .ok_or_else(|| {
+                Error::BadRequest(format!("Release {} has no earlier revision", name))
+            })?,
+    };
+    let release = Release {
+        revision: next_revision(&revisions),
+        status: DEPLOYED.to_owned(),
+        updated: now(),
+        ..target.clone()
+    };
+    deploy(client, &secret_api, Some(previous), release).await
+}
+
+/// Deletes the objects of the current revision and forgets the release.
+pub async fn uninstall(
+    client: Arc<Client>,
+    namespace: &str,
+    name: &str,
+) -> Result<Vec<DeleteResult>> {
+    let secret_api: Api<Secret> = Api::namespaced(client.as_ref().clone(), namespace);
+    let revisions = revisions(&secret_api, name).await?;
+    let release = current(&revisions).ok_or_else(|| not_found(name))?;
+    let container = deployment_service::documents(release.manifest.as_bytes())?;
+    let results = deployment_service::delete_resources(
+        container,
+        namespace,
+        client,
+        &DeleteManifestParams::default(),
+    )
+    .await?;
+    secret_api
+        .delete_collection(&DeleteParams::default(), &release_selector(name))
+        .await?;
+    info!("Release {} uninstalled", name);
+    Ok(results)
+}
+
+/// Records `release` as a pending revision, server-side applies its
+/// manifest, prunes what `previous` had that `release` dropped, and marks
+/// the revision deployed. Recording first makes a concurrent deploy of the
+/// same revision fail before it touches the cluster. Nothing is pruned and
+/// older revisions stay current if any document fails to apply.
+async fn deploy(
+    client: Arc<Client>,
+    secret_api: &Api<Secret>,
+    previous: Option<&Release>,
+    mut release: Release,
+) -> Result<ReleaseReport> {
+    release.status = PENDING.to_owned();
+    record(secret_api, &release).await?;
+
+    let container = deployment_service::documents(release.manifest.as_bytes())?;
+    let params = ManifestParams {
+        apply: Some(true),
+        ..ManifestParams::default()
+    };
+    let results =
+        deployment_service::apply_resources(container, &release.namespace, client.clone(), &params)
+            .await;
+    let failed = results.iter().any(|result| result.error.is_some());
+
+    let mut pruned = Vec::new();
+    if let (Some(previous), false) = (previous, failed) {
+        let stale = stale_documents(&previous.manifest, &release.manifest, &release.namespace)?;
+        pruned = deployment_service::delete_resources(
+            stale,
+            &release.namespace,
+            client,
+            &DeleteManifestParams::default(),
+        )
+        .await?;
+    }
+
+    let status = if failed { FAILED } else { DEPLOYED };
+    set_status(secret_api, &release, status).await?;
+    release.status = status.to_owned();
+    if !failed {
+        for older in revisions(secret_api, &release.name).await? {
+            if older.revision < release.revision && older.status == DEPLOYED {
+                set_status(secret_api, &older, SUPERSEDED).await?;
+            }
+        }
+    }
+    info!(
+        "Release {} revision {} {}",
+        release.name, release.revision, release.status
+    );
+    Ok(ReleaseReport {
+        release: summary(&release),
+        results,
+        pruned,
+    })
+}
+
+/// Documents of `old` whose object is not part of `new` any more. Objects
+/// are told apart by API group, kind, namespace and name, so a kind moving
+/// to another version of its group is not mistaken for a dropped object.
+fn stale_documents(old: &str, new: &str, namespace: &str) -> Result<Vec<serde_yaml::Value>> {
+    let key = |doc: &serde_yaml::Value| {
+        let api_version = doc["apiVersion"].as_str().unwrap_or_default();
+        let group = api_version
+            .rsplit_once('/')
+            .map_or("", |(group, _)| group)
+            .to_owned();
+        (
+            group,
+            doc["kind"].as_str().unwrap_or_default().to_owned(),
+            doc["metadata"]["namespace"]
+                .as_str()
+                .unwrap_or(namespace)
+                .to_owned(),
+            doc["metadata"]["name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned(),
+        )
+    };
+    let kept: HashSet<_> = deployment_service::documents(new.as_bytes())?
+        .iter()
+        .map(key)
+        .collect();
+    Ok(deployment_service::documents(old.as_bytes())?
+        .into_iter()
+        .filter(|doc| !doc.is_null() && !kept.contains(&key(doc)))
+        .collect())
+}
+
+/// The revision the cluster runs: the newest deployed one, or the newest
+/// of all while none has been deployed yet.
+fn current(revisions: &[Release]) -> Option<&Release> {
+    revisions
+        .iter()
+        .rev()
+        .find(|release| release.status == DEPLOYED)
+        .or_else(|| revisions.last())
+}
+
+fn next_revision(revisions: &[Release]) -> u32 {
+    revisions.last().map_or(1, |release| release.revision + 1)
+}
+
+/// Every revision of a release, oldest first.
+async fn revisions(secret_api: &Api<Secret>, name: &str) -> Result<Vec<Release>> {
+    let mut revisions = secret_api
+        .list(&release_selector(name))
+        .await?
+        .iter()
+        .map(decode)
+        .collect::<Result<Vec<_>>>()?;
+    revisions.sort_by_key(|release| release.revision);
+    Ok(revisions)
+}
+
+async fn record(secret_api: &Api<Secret>, release: &Release) -> Result<()> {
+    let mut secret = Secret {
+        type_: Some("kube-sphere.io/release.v1".to_owned()),
+        data: Some(BTreeMap::from([(
+            RELEASE_KEY.to_owned(),
+            ByteString(serde_json::to_vec(release)?),
+        )])),
+        ..Secret::default()
+    };
+    secret.metadata.name = Some(format!(
+        "kube-sphere.release.v1.{}.v{}",
+        release.name, release.revision
+    ));
+    secret.metadata.labels = Some(BTreeMap::from([
+        (OWNER_LABEL.to_owned(), OWNER.to_owned()),
+        (NAME_LABEL.to_owned(), release.name.clone()),
+        (REVISION_LABEL.to_owned(), release.revision.to_string()),
+        (STATUS_LABEL.to_owned(), release.status.clone()),
+    ]));
+    match secret_api.create(&PostParams::default(), &secret).await {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(ae)) if ae.code == 409 => Err(Error::ResourceAlreadyExists(anyhow!(
+            "Revision {} of release {} is already being deployed",
+            release.revision,
+            release.name
+        ))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn set_status(secret_api: &Api<Secret>, release: &Release, status: &str) -> Result<()> {
+    let release = Release {
+        status: status.to_owned(),
+        ..release.clone()
+    };
+    let patch = serde_json::json!({
+        "metadata": { "labels": { STATUS_LABEL: status } },
+        "data": { RELEASE_KEY: ByteString(serde_json::to_vec(&release)?) },
+    });
+    secret_api
+        .patch(
+            &format!(
+                "kube-sphere.release.v1.{}.v{}",
+                release.name, release.revision
+            ),
+            &PatchParams::default(),
+            &Patch::Merge(&patch),
+        )
+        .await?;
+    Ok(())
+}
+
+fn decode(secret: &Secret) -> Result<Release> {
+    let data = secret
+        .data
+        .as_ref()
+        .and_then(|data| data.get(RELEASE_KEY))
+        .ok_or_else(|| {
+            Error::General(anyhow!(
+                "Secret {} holds no release",
+                secret.metadata.name.as_deref().unwrap_or_default()
+            ))
+        })?;
+    Ok(serde_json::from_slice(&data.0)?)
+}
+
+fn release_selector(name: &str) -> ListParams {
+    ListParams::default().labels(&format!(
+        "{}={},{}={}",
+        OWNER_LABEL, OWNER, NAME_LABEL, name
+    ))
+}
+
+fn summary(release: &Release) -> ReleaseSummary {
+    ReleaseSummary {
+        name: release.name.clone(),
+        revision: release.revision,
+        template: release.template.clone(),
+        status: release.status.clone(),
+        updated: release.updated.clone(),
+    }
+}
+
+fn not_found(name: &str) -> Error {
+    Error::ResourceNotFound(format!("Release {} not found", name))
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(docs: &[serde_yaml::Value]) -> Vec<&str> {
+        docs.iter()
+            .map(|doc| doc["metadata"]["name"].as_str().unwrap())
+            .collect()
+    }
+
+    fn release(revision: u32, status: &str) -> Release {
+        Release {
+            name: "shop".to_owned(),
+            namespace: "default".to_owned(),
+            revision,
+            template: "nginx".to_owned(),
+            values: serde_json::Value::Null,
+            manifest: String::new(),
+            status: status.to_owned(),
+            updated: String::new(),
+        }
+    }
+
+    #[test]
+    fn removed_objects_are_stale() {
+        let old = "apiVersion: v1\nkind: Service\nmetadata:\n  name: web\n\
+                   ---\napiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: settings\n";
+        let new = "apiVersion: v1\nkind: Service\nmetadata:\n  name: web\n";
+        let stale = stale_documents(old, new, "default").unwrap();
+        assert_eq!(names(&stale), vec!["settings"]);
+    }
+
+    #[test]
+    fn renamed_objects_are_stale_and_kept_ones_are_not() {
+        let old = "apiVersion: v1\nkind: Service\nmetadata:\n  name: web\n\
+                   ---\napiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: settings\n";
+        let new = "apiVersion: v1\nkind: Service\nmetadata:\n  name: frontend\n\
+                   ---\napiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: settings\n";
+        let stale = stale_documents(old, new, "default").unwrap();
+        assert_eq!(names(&stale), vec!["web"]);
+        assert!(stale_documents(old, old, "default").unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_version_move_within_a_group_is_not_stale() {
+        let old =
+            "apiVersion: autoscaling/v1\nkind: HorizontalPodAutoscaler\nmetadata:\n  name: web\n";
+        let new =
+            "apiVersion: autoscaling/v2\nkind: HorizontalPodAutoscaler\nmetadata:\n  name: web\n";
+        assert!(stale_documents(old, new, "default").unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_move_to_another_group_or_namespace_is_stale() {
+        let old = "apiVersion: extensions/v1beta1\nkind: Ingress\nmetadata:\n  name: web\n\
+                   ---\napiVersion: v1\nkind: Service\nmetadata:\n  name: api\n";
+        let new = "apiVersion: networking.k8s.io/v1\nkind: Ingress\nmetadata:\n  name: web\n\
+                   ---\napiVersion: v1\nkind: Service\nmetadata:\n  name: api\n  namespace: other\n";
+        let stale = stale_documents(old, new, "default").unwrap();
+        assert_eq!(names(&stale), vec!["web", "api"]);
+    }
+
+    #[test]
+    fn the_release_namespace_matches_documents_without_one() {
+        let old = "apiVersion: v1\nkind: Service\nmetadata:\n  name: web\n  namespace: default\n";
+        let new = "apiVersion: v1\nkind: Service\nmetadata:\n  name: web\n";
+        assert!(stale_documents(old, new, "default").unwrap().is_empty());
+        assert!(stale_documents(new, old, "default").unwrap().is_empty());
+    }
+
+    #[test]
+    fn current_is_the_newest_deployed_revision() {
+        let revisions = [
+            release(1, SUPERSEDED),
+            release(2, DEPLOYED),
+            release(3, FAILED),
+        ];
+        assert_eq!(current(&revisions).unwrap().revision, 2);
+        assert_eq!(next_revision(&revisions), 4);
+    }
+
+    #[test]
+    fn current_falls_back_to_the_newest_revision() {
+        let revisions = [release(1, FAILED), release(2, PENDING)];
+        assert_eq!(current(&revisions).unwrap().revision, 2);
+        assert!(current(&[]).is_none());
+        assert_eq!(next_revision(&[]), 1);
+    }
+}
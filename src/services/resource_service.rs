@@ -0,0 +1,213 @@
+use crate::models::deployment::PatchQuery;
+use crate::models::resource::{ResourcePath, ResourceQuery};
+use crate::{Error, Result};
+use kube::api::{Api, DeleteParams, DynamicObject, ListParams, Patch, PatchParams, PostParams};
+use kube::core::{GroupVersion, GroupVersionKind};
+use kube::discovery::{self, ApiCapabilities, ApiResource, Scope};
+use kube::{Client, ResourceExt};
+use log::*;
+use std::sync::Arc;
+
+pub const FIELD_MANAGER: &str = "kube-sphere";
+
+/// Resolves `kind` of `api_version` through discovery and returns an API
+/// for it, scoped to `namespace` unless the kind is cluster-scoped.
+pub(crate) async fn api_for_kind(
+    client: &Client,
+    namespace: &str,
+    api_version: &str,
+    kind: &str,
+) -> Result<Api<DynamicObject>> {
+    let (group, version) = api_version.split_once('/').unwrap_or(("", api_version));
+    let gvk = GroupVersionKind::gvk(group, version, kind);
+    let (resource, caps) = discovery::pinned_kind(client, &gvk)
+        .await
+        .map_err(|e| not_served(e, format!("{} {}", api_version, kind)))?;
+    Ok(match caps.scope {
+        Scope::Cluster => Api::all_with(client.clone(), &resource),
+        Scope::Namespaced => Api::namespaced_with(client.clone(), namespace, &resource),
+    })
+}
+
+/// Lists the resources at `path`. Namespaced resources listed through the
+/// cluster-scoped path are listed across all namespaces.
+pub async fn list(
+    client: Arc<Client>,
+    path: &ResourcePath,
+    query: &ResourceQuery,
+) -> Result<Vec<DynamicObject>> {
+    let api = api_for_path(&client, path, true).await?;
+    let lp = ListParams::default()
+        .labels(query.labels.as_deref().unwrap_or_default())
+        .fields(query.fields.as_deref().unwrap_or_default());
+    let list = api.list(&lp).await?;
+    info!("Found {} {}", list.items.len(), path.plural);
+    Ok(list.items)
+}
+
+pub async fn get(client: Arc<Client>, path: &ResourcePath) -> Result<DynamicObject> {
+    let api = api_for_path(&client, path, false).await?;
+    Ok(api.get(name_of(path)?).await?)
+}
+
+pub async fn create(
+    client: Arc<Client>,
+    path: &ResourcePath,
+    mut object: DynamicObject,
+) -> Result<DynamicObject> {
+    let api = api_for_path(&client, path, false).await?;
+    object.metadata.namespace = path.namespace.clone();
+    match api.create(&PostParams::default(), &object).await {
+        Ok(created) => {
+            info!("Created {}: {}", path.plural, created.name_any());
+            Ok(created)
+        }
+        Err(kube::Error::Api(ae)) if ae.code == 409 => {
+            Err(Error::ResourceAlreadyExists(anyhow::anyhow!(
+                "Resource {} {} already exists",
+                path.plural,
+                object.name_any()
+            )))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn replace(
+    client: Arc<Client>,
+    path: &ResourcePath,
+    mut object: DynamicObject,
+) -> Result<DynamicObject> {
+    let api = api_for_path(&client, path, false).await?;
+    let name = name_of(path)?;
+    object.metadata.namespace = path.namespace.clone();
+    object.metadata.name = Some(name.to_owned());
+    let replaced = api.replace(name, &PostParams::default(), &object).await?;
+    info!("Replaced {}: {}", path.plural, name);
+    Ok(replaced)
+}
+
+pub async fn patch(
+    client: Arc<Client>,
+    path: &ResourcePath,
+    query: PatchQuery,
+    content_type: Option<&str>,
+    body: &[u8],
+) -> Result<DynamicObject> {
+    let api = api_for_path(&client, path, false).await?;
+    let name = name_of(path)?;
+    let (patch_params, patch) = patch_request(query, content_type, body)?;
+    let patched = api.patch(name, &patch_params, &patch).await?;
+    info!("Patched {}: {}", path.plural, name);
+    Ok(patched)
+}
+
+pub async fn delete(client: Arc<Client>, path: &ResourcePath) -> Result<String> {
+    let api = api_for_path(&client, path, false).await?;
+    let name = name_of(path)?;
+    api.delete(name, &DeleteParams::default()).await?;
+    info!("Deleted {}: {}", path.plural, name);
+    Ok("OK".to_owned())
+}
+
+/// Builds the patch described by `query`, the request content type and
+/// `body`, shared by every endpoint that accepts patches.
+pub(crate) fn patch_request(
+    query: PatchQuery,
+    content_type: Option<&str>,
+    body: &[u8],
+) -> Result<(PatchParams, Patch<serde_json::Value>)> {
+    let patch_type = query.type_.as_deref().or(match content_type {
+        Some(ct) if ct.starts_with("application/strategic-merge-patch") => Some("strategic"),
+        Some(ct) if ct.starts_with("application/json-patch") => Some("json"),
+        Some(ct) if ct.starts_with("application/apply-patch") => Some("apply"),
+        _ => None,
+    });
+    let field_manager = query
+        .field_manager
+        .unwrap_or_else(|| FIELD_MANAGER.to_owned());
+    let invalid = |e: &dyn std::fmt::Display| Error::BadRequest(format!("Invalid patch: {}", e));
+
+    let mut patch_params = PatchParams {
+        field_manager: Some(field_manager.clone()),
+        ..PatchParams::default()
+    };
+    let patch: Patch<serde_json::Value> = match patch_type.unwrap_or("merge") {
+        "merge" => Patch::Merge(serde_json::from_slice(body).map_err(|e| invalid(&e))?),
+        "strategic" => Patch::Strategic(serde_json::from_slice(body).map_err(|e| invalid(&e))?),
+        "json" => Patch::Json(serde_json::from_slice(body).map_err(|e| invalid(&e))?),
+        "apply" => {
+            patch_params = PatchParams::apply(&field_manager);
+            if query.force.unwrap_or_default() {
+                patch_params = patch_params.force();
+            }
+            // apply patches are usually YAML, which also covers JSON
+            Patch::Apply(serde_yaml::from_slice(body).map_err(|e| invalid(&e))?)
+        }
+        other => {
+            return Err(Error::BadRequest(format!(
+                "Unsupported patch type {}, expected merge, strategic, json or apply",
+                other
+            )))
+        }
+    };
+    Ok((patch_params, patch))
+}
+
+/// Resolves the resource of `path` through discovery. Only listing may
+/// address a namespaced resource without a namespace.
+async fn api_for_path(
+    client: &Client,
+    path: &ResourcePath,
+    list: bool,
+) -> Result<Api<DynamicObject>> {
+    let (resource, caps) = resolve(client, path).await?;
+    match (caps.scope, path.namespace.as_deref()) {
+        (Scope::Namespaced, Some(namespace)) => {
+            Ok(Api::namespaced_with(client.clone(), namespace, &resource))
+        }
+        (Scope::Namespaced, None) if !list => Err(Error::BadRequest(format!(
+            "{} are namespaced, use /{}/{}/namespaces/:namespace/{}",
+            path.plural, path.group, path.version, path.plural
+        ))),
+        (Scope::Cluster, Some(_)) => Err(Error::BadRequest(format!(
+            "{} are cluster-scoped, use /{}/{}/{}",
+            path.plural, path.group, path.version, path.plural
+        ))),
+        _ => Ok(Api::all_with(client.clone(), &resource)),
+    }
+}
+
+async fn resolve(client: &Client, path: &ResourcePath) -> Result<(ApiResource, ApiCapabilities)> {
+    // the legacy core group has no name, `core` stands in for it in paths
+    let group = if path.group == "core" {
+        ""
+    } else {
+        &path.group
+    };
+    let what = format!("{}/{} {}", path.group, path.version, path.plural);
+    let api_group = discovery::pinned_group(client, &GroupVersion::gv(group, &path.version))
+        .await
+        .map_err(|e| not_served(e, what.clone()))?;
+    api_group
+        .versioned_resources(&path.version)
+        .into_iter()
+        .find(|(resource, _)| resource.plural == path.plural)
+        .ok_or(Error::UnsupportedKind(what))
+}
+
+fn name_of(path: &ResourcePath) -> Result<&str> {
+    path.name
+        .as_deref()
+        .ok_or_else(|| Error::BadRequest("Missing resource name".to_owned()))
+}
+
+/// Turns discovery failures of an unknown group, version or kind into
+/// `UnsupportedKind`, leaving other errors as they are.
+fn not_served(err: kube::Error, what: String) -> Error {
+    match err {
+        kube::Error::Discovery(_) => Error::UnsupportedKind(what),
+        kube::Error::Api(ae) if ae.code == 404 => Error::UnsupportedKind(what),
+        err => err.into(),
+    }
+}
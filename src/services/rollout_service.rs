@@ -0,0 +1,1024 @@
+use crate::models::deployment::{
+    ConditionSummary, Revision, RolloutState, RolloutStatus, SetImageParams,
+};
+use crate::{Error, Result};
+use anyhow::anyhow;
+use futures::{stream, Stream, StreamExt};
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet};
+use k8s_openapi::api::batch::v1::CronJob;
+use k8s_openapi::api::core::v1::PodSpec;
+use k8s_openapi::chrono::{SecondsFormat, Utc};
+use k8s_openapi::NamespaceResourceScope;
+use kube::api::{Api, ListParams, Patch, PatchParams, PostParams};
+use kube::core::ObjectMeta;
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::Client;
+use kube::Resource;
+use log::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::json;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_ROLLOUT_TIMEOUT: Duration = Duration::from_secs(600);
+
+pub async fn status<K: Workload>(
+    client: Arc<Client>,
+    namespace: &str,
+    name: &str,
+) -> Result<RolloutStatus> {
+    let api: Api<K> = Api::namespaced(client.as_ref().clone(), namespace);
+    Ok(api.get(name).await?.rollout_status())
+}
+
+/// Waits until the rollout of a workload completes, fails or `timeout`
+/// passes, and returns the last status seen.
+pub async fn wait<K: Workload>(
+    client: Arc<Client>,
+    namespace: &str,
+    name: &str,
+    timeout: Duration,
+) -> Result<RolloutStatus> {
+    let statuses = watch::<K>(client.clone(), namespace, name, timeout).await?;
+    let mut statuses = Box::pin(statuses);
+    let mut last = None;
+    while let Some(status) = statuses.next().await {
+        last = Some(status);
+    }
+    match last {
+        Some(status) => Ok(status),
+        None => status::<K>(client, namespace, name).await,
+    }
+}
+
+/// Streams the rollout status of a workload on every change. The stream
+/// ends after the first `Complete` or `Failed` status, or with a `TimedOut`
+/// status once `timeout` passes.
+pub async fn watch<K: Workload>(
+    client: Arc<Client>,
+    namespace: &str,
+    name: &str,
+    timeout: Duration,
+) -> Result<impl Stream<Item = RolloutStatus> + Send> {
+    let api: Api<K> = Api::namespaced(client.as_ref().clone(), namespace);
+    // fail with a 404 right away rather than waiting on a missing workload
+    api.get(name).await?;
+    info!(
+        "Waiting for rollout of {} {} in namespace {}",
+        K::kind(&()),
+        name,
+        namespace
+    );
+
+    let config = watcher::Config::default().fields(&format!("metadata.name={}", name));
+    let name = name.to_owned();
+    let workload = name.clone();
+    let statuses = watcher(api, config)
+        .backoff(watcher::default_backoff())
+        .filter_map(move |event| {
+            let status = match event {
+                Ok(watcher::Event::Applied(workload)) => Some(workload.rollout_status()),
+                Ok(watcher::Event::Restarted(workloads)) => {
+                    workloads.first().map(|workload| workload.rollout_status())
+                }
+                Ok(watcher::Event::Deleted(workload)) => {
+                    let mut status = workload.rollout_status();
+                    status.state = RolloutState::Failed;
+                    status.message = format!("{} {} was deleted", K::kind(&()), name);
+                    Some(status)
+                }
+                Err(e) => {
+                    warn!("Watching {} {} failed: {}", K::kind(&()), name, e);
+                    None
+                }
+            };
+            futures::future::ready(status)
+        });
+    Ok(until_settled(statuses, workload, timeout))
+}
+
+/// Ends `statuses` after the first settled status, or after `timeout` with
+/// the last status of workload `name` marked as timed out.
+fn until_settled(
+    statuses: impl Stream<Item = RolloutStatus> + Send + 'static,
+    name: String,
+    timeout: Duration,
+) -> impl Stream<Item = RolloutStatus> + Send {
+    let deadline = Box::pin(tokio::time::sleep(timeout));
+    let state = (statuses.boxed(), deadline, None::<RolloutStatus>, false);
+    stream::unfold(state, move |(mut statuses, mut deadline, last, done)| {
+        let name = name.clone();
+        async move {
+            if done {
+                return None;
+            }
+            tokio::select! {
+                status = statuses.next() => {
+                    let status = status?;
+                    let done = status.state != RolloutState::Progressing;
+                    Some((status.clone(), (statuses, deadline, Some(status), done)))
+                }
+                _ = &mut deadline => {
+                    let status = timed_out(name, last);
+                    Some((status, (statuses, deadline, None, true)))
+                }
+            }
+        }
+    })
+}
+
+/// Marks the last status seen as timed out. Without one, e.g. when the
+/// watch never got through, a status carrying only the name is made up.
+fn timed_out(name: String, last: Option<RolloutStatus>) -> RolloutStatus {
+    match last {
+        Some(mut status) => {
+            status.state = RolloutState::TimedOut;
+            status.message = format!("Timed out waiting for rollout: {}", status.message);
+            status
+        }
+        None => RolloutStatus {
+            name,
+            state: RolloutState::TimedOut,
+            message: "Timed out waiting for rollout, no status was observed".to_owned(),
+            generation: None,
+            observed_generation: None,
+            replicas: 0,
+            updated_replicas: 0,
+            ready_replicas: 0,
+            available_replicas: 0,
+            unavailable_replicas: 0,
+            paused: false,
+            conditions: Vec::new(),
+        },
+    }
+}
+
+/// A namespaced resource that creates pods from an embedded pod template.
+pub trait PodTemplate:
+    Resource<Scope = NamespaceResourceScope, DynamicType = ()>
+    + Clone
+    + DeserializeOwned
+    + Serialize
+    + Debug
+    + Send
+    + Sync
+    + 'static
+{
+    fn pod_spec_mut(&mut self) -> Option<&mut PodSpec>;
+}
+
+/// A pod template resource whose pods are rolled out by a controller.
+pub trait Workload: PodTemplate {
+    fn rollout_status(&self) -> RolloutStatus;
+}
+
+impl PodTemplate for Deployment {
+    fn pod_spec_mut(&mut self) -> Option<&mut PodSpec> {
+        self.spec.as_mut()?.template.spec.as_mut()
+    }
+}
+
+impl PodTemplate for StatefulSet {
+    fn pod_spec_mut(&mut self) -> Option<&mut PodSpec> {
+        self.spec.as_mut()?.template.spec.as_mut()
+    }
+}
+
+impl PodTemplate for DaemonSet {
+    fn pod_spec_mut(&mut self) -> Option<&mut PodSpec> {
+        self.spec.as_mut()?.template.spec.as_mut()
+    }
+}
+
+impl PodTemplate for CronJob {
+    fn pod_spec_mut(&mut self) -> Option<&mut PodSpec> {
+        self.spec
+            .as_mut()?
+            .job_template
+            .spec
+            .as_mut()?
+            .template
+            .spec
+            .as_mut()
+    }
+}
+
+impl Workload for Deployment {
+    fn rollout_status(&self) -> RolloutStatus {
+        rollout_status(self)
+    }
+}
+
+impl Workload for StatefulSet {
+    fn rollout_status(&self) -> RolloutStatus {
+        statefulset_rollout_status(self)
+    }
+}
+
+impl Workload for DaemonSet {
+    fn rollout_status(&self) -> RolloutStatus {
+        daemonset_rollout_status(self)
+    }
+}
+
+/// Restarts every pod of a workload by stamping its pod template, like
+/// `kubectl rollout restart`.
+pub async fn restart<K: Workload>(
+    client: Arc<Client>,
+    namespace: &str,
+    name: &str,
+) -> Result<RolloutStatus> {
+    let api: Api<K> = Api::namespaced(client.as_ref().clone(), namespace);
+    let patch = json!({
+        "spec": {
+            "template": {
+                "metadata": {
+                    "annotations": {
+                        "kubectl.kubernetes.io/restartedAt": Utc::now().to_rfc3339()
+                    }
+                }
+            }
+        }
+    });
+    let workload = api
+        .patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+        .await?;
+    info!("{} {} restarted", K::kind(&()), name);
+    Ok(workload.rollout_status())
+}
+
+/// Pauses or resumes the rollout of a deployment.
+pub async fn set_paused(
+    client: Arc<Client>,
+    namespace: &str,
+    deployment_name: &str,
+    paused: bool,
+) -> Result<RolloutStatus> {
+    let deployment_api: Api<Deployment> = Api::namespaced(client.as_ref().clone(), namespace);
+    let patch = json!({ "spec": { "paused": paused } });
+    let deployment = deployment_api
+        .patch(
+            deployment_name,
+            &PatchParams::default(),
+            &Patch::Merge(&patch),
+        )
+        .await?;
+    info!(
+        "Deployment {} {}",
+        deployment_name,
+        if paused { "paused" } else { "resumed" }
+    );
+    Ok(rollout_status(&deployment))
+}
+
+/// Sets the replica count of a workload through its scale subresource.
+pub async fn scale<K: Workload>(
+    client: Arc<Client>,
+    namespace: &str,
+    name: &str,
+    replicas: i32,
+) -> Result<RolloutStatus> {
+    if replicas < 0 {
+        return Err(Error::BadRequest(
+            "replicas must not be negative".to_owned(),
+        ));
+    }
+    let api: Api<K> = Api::namespaced(client.as_ref().clone(), namespace);
+    let patch = json!({ "spec": { "replicas": replicas } });
+    api.patch_scale(name, &PatchParams::default(), &Patch::Merge(&patch))
+        .await?;
+    info!("{} {} scaled to {}", K::kind(&()), name, replicas);
+    Ok(api.get(name).await?.rollout_status())
+}
+
+/// Sets the image of named containers and init containers of a resource
+/// and records the change as its change-cause.
+pub async fn set_image<K: PodTemplate>(
+    client: Arc<Client>,
+    namespace: &str,
+    name: &str,
+    params: &SetImageParams,
+) -> Result<(K, Vec<String>)> {
+    let api: Api<K> = Api::namespaced(client.as_ref().clone(), namespace);
+    let mut resource = api.get(name).await?;
+    let pod_spec = resource
+        .pod_spec_mut()
+        .ok_or_else(|| Error::General(anyhow!("{} {} has no pod template", K::kind(&()), name)))?;
+
+    let mut updated = Vec::new();
+    let mut missing = Vec::new();
+    for target in &params.containers {
+        let container = pod_spec
+            .containers
+            .iter_mut()
+            .chain(pod_spec.init_containers.iter_mut().flatten())
+            .find(|container| container.name == target.name);
+        let Some(container) = container else {
+            missing.push(target.name.clone());
+            continue;
+        };
+        let image = match (&target.image, &target.tag) {
+            (Some(image), _) => image.clone(),
+            (None, Some(tag)) => with_tag(container.image.as_deref().unwrap_or_default(), tag),
+            (None, None) => {
+                return Err(Error::BadRequest(format!(
+                    "Container {} needs an image or a tag",
+                    target.name
+                )))
+            }
+        };
+        updated.push(format!("{}={}", container.name, image));
+        container.image = Some(image);
+    }
+    if !missing.is_empty() {
+        return Err(Error::BadRequest(format!(
+            "{} {} has no container named {}",
+            K::kind(&()),
+            name,
+            missing.join(", ")
+        )));
+    }
+
+    let change_cause = params
+        .change_cause
+        .clone()
+        .unwrap_or_else(|| format!("kube-sphere set image {}", updated.join(" ")));
+    resource
+        .meta_mut()
+        .annotations
+        .get_or_insert_with(Default::default)
+        .insert(CHANGE_CAUSE_ANNOTATION.to_owned(), change_cause);
+    let resource = api.replace(name, &PostParams::default(), &resource).await?;
+    info!("{} {} image updated: {:?}", K::kind(&()), name, updated);
+    Ok((resource, updated))
+}
+
+/// Replaces the tag of an image reference, dropping any digest.
+fn with_tag(image: &str, tag: &str) -> String {
+    let image = image.split('@').next().unwrap_or_default();
+    let name_start = image.rfind('/').map_or(0, |slash| slash + 1);
+    let repository = match image[name_start..].find(':') {
+        Some(colon) => &image[..name_start + colon],
+        None => image,
+    };
+    format!("{}:{}", repository, tag)
+}
+
+const REVISION_ANNOTATION: &str = "deployment.kubernetes.io/revision";
+const CHANGE_CAUSE_ANNOTATION: &str = "kubernetes.io/change-cause";
+
+/// Lists the revisions of a deployment from the ReplicaSets it owns, oldest
+/// first, like `kubectl rollout history`.
+pub async fn history(
+    client: Arc<Client>,
+    namespace: &str,
+    deployment_name: &str,
+) -> Result<Vec<Revision>> {
+    let deployment_api: Api<Deployment> = Api::namespaced(client.as_ref().clone(), namespace);
+    let deployment = deployment_api.get(deployment_name).await?;
+    let current = revision_of(&deployment.metadata);
+    let revisions = owned_replica_sets(client, namespace, &deployment)
+        .await?
+        .iter()
+        .map(|replica_set| Revision {
+            revision: revision_of(&replica_set.metadata),
+            replica_set: replica_set.metadata.name.clone().unwrap_or_default(),
+            images: replica_set
+                .spec
+                .as_ref()
+                .and_then(|spec| spec.template.as_ref())
+                .and_then(|template| template.spec.as_ref())
+                .map(|spec| {
+                    spec.containers
+                        .iter()
+                        .filter_map(|container| container.image.clone())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            change_cause: annotation(&replica_set.metadata, CHANGE_CAUSE_ANNOTATION),
+            created_at: replica_set
+                .metadata
+                .creation_timestamp
+                .as_ref()
+                .map(|time| time.0.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            replicas: replica_set
+                .status
+                .as_ref()
+                .map(|status| status.replicas)
+                .unwrap_or_default(),
+            current: revision_of(&replica_set.metadata) == current,
+        })
+        .collect();
+    Ok(revisions)
+}
+
+/// Rolls a deployment back to the pod template of an earlier revision, like
+/// `kubectl rollout undo`. Without a revision the previous one is restored.
+pub async fn rollback(
+    client: Arc<Client>,
+    namespace: &str,
+    deployment_name: &str,
+    revision: Option<i64>,
+) -> Result<RolloutStatus> {
+    let deployment_api: Api<Deployment> = Api::namespaced(client.as_ref().clone(), namespace);
+    let mut deployment = deployment_api.get(deployment_name).await?;
+    let current = revision_of(&deployment.metadata);
+    let replica_sets = owned_replica_sets(client, namespace, &deployment).await?;
+    let target = match revision {
+        Some(revision) => replica_sets
+            .iter()
+            .find(|replica_set| revision_of(&replica_set.metadata) == revision),
+        None => replica_sets
+            .iter()
+            .rfind(|replica_set| revision_of(&replica_set.metadata) < current),
+    }
+    .ok_or_else(|| match revision {
+        Some(revision) => Error::BadRequest(format!(
+            "Revision {} of deployment {} not found",
+            revision, deployment_name
+        )),
+        None => Error::BadRequest(format!(
+            "Deployment {} has no previous revision",
+            deployment_name
+        )),
+    })?;
+
+    let mut template = target
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.template.clone())
+        .ok_or_else(|| Error::General(anyhow!("ReplicaSet has no pod template")))?;
+    // the hash label is added by the deployment controller for each ReplicaSet
+    if let Some(labels) = template.metadata.as_mut().and_then(|m| m.labels.as_mut()) {
+        labels.remove("pod-template-hash");
+    }
+    let spec = deployment
+        .spec
+        .as_mut()
+        .ok_or_else(|| Error::General(anyhow!("Deployment has no spec")))?;
+    if spec.template == template {
+        info!(
+            "Deployment {} already runs the template of revision {}",
+            deployment_name,
+            revision_of(&target.metadata)
+        );
+        return Ok(rollout_status(&deployment));
+    }
+    spec.template = template;
+    let annotations = deployment
+        .metadata
+        .annotations
+        .get_or_insert_with(Default::default);
+    match annotation(&target.metadata, CHANGE_CAUSE_ANNOTATION) {
+        Some(change_cause) => annotations.insert(CHANGE_CAUSE_ANNOTATION.to_owned(), change_cause),
+        None => annotations.remove(CHANGE_CAUSE_ANNOTATION),
+    };
+
+    let updated = deployment_api
+        .replace(deployment_name, &PostParams::default(), &deployment)
+        .await?;
+    info!(
+        "Deployment {} rolled back to revision {}",
+        deployment_name,
+        revision_of(&target.metadata)
+    );
+    Ok(rollout_status(&updated))
+}
+
+/// The ReplicaSets controlled by a deployment, ordered by revision.
+async fn owned_replica_sets(
+    client: Arc<Client>,
+    namespace: &str,
+    deployment: &Deployment,
+) -> Result<Vec<ReplicaSet>> {
+    let replica_set_api: Api<ReplicaSet> = Api::namespaced(client.as_ref().clone(), namespace);
+    let selector = deployment
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.selector.match_labels.as_ref())
+        .map(|labels| {
+            labels
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .unwrap_or_default();
+    let uid = deployment.metadata.uid.as_deref();
+    let mut replica_sets: Vec<ReplicaSet> = replica_set_api
+        .list(&ListParams::default().labels(&selector))
+        .await?
+        .items
+        .into_iter()
+        .filter(|replica_set| {
+            replica_set
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .any(|owner| owner.controller == Some(true) && Some(owner.uid.as_str()) == uid)
+        })
+        .collect();
+    replica_sets.sort_by_key(|replica_set| revision_of(&replica_set.metadata));
+    Ok(replica_sets)
+}
+
+fn revision_of(metadata: &ObjectMeta) -> i64 {
+    annotation(metadata, REVISION_ANNOTATION)
+        .and_then(|revision| revision.parse().ok())
+        .unwrap_or_default()
+}
+
+fn annotation(metadata: &ObjectMeta, key: &str) -> Option<String> {
+    metadata.annotations.as_ref()?.get(key).cloned()
+}
+
+/// Computes the rollout status of a deployment following the rules of
+/// `kubectl rollout status`.
+pub fn rollout_status(deployment: &Deployment) -> RolloutStatus {
+    let name = deployment.metadata.name.clone().unwrap_or_default();
+    let spec = deployment.spec.as_ref();
+    let status = deployment.status.clone().unwrap_or_default();
+    let generation = deployment.metadata.generation;
+    let desired = spec.and_then(|spec| spec.replicas).unwrap_or(1);
+    let replicas = status.replicas.unwrap_or_default();
+    let updated = status.updated_replicas.unwrap_or_default();
+    let available = status.available_replicas.unwrap_or_default();
+    let conditions: Vec<ConditionSummary> = status
+        .conditions
+        .iter()
+        .flatten()
+        .map(|condition| ConditionSummary {
+            type_: condition.type_.clone(),
+            status: condition.status.clone(),
+            reason: condition.reason.clone(),
+            message: condition.message.clone(),
+            last_update_time: condition
+                .last_update_time
+                .as_ref()
+                .map(|time| time.0.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        })
+        .collect();
+    let deadline_exceeded = conditions.iter().any(|condition| {
+        condition.type_ == "Progressing"
+            && condition.reason.as_deref() == Some("ProgressDeadlineExceeded")
+    });
+
+    let (state, message) = if generation > status.observed_generation {
+        (
+            RolloutState::Progressing,
+            "Waiting for deployment spec update to be observed".to_owned(),
+        )
+    } else if deadline_exceeded {
+        (
+            RolloutState::Failed,
+            format!("Deployment {} exceeded its progress deadline", name),
+        )
+    } else if updated < desired {
+        (
+            RolloutState::Progressing,
+            format!(
+                "Waiting for rollout to finish: {} out of {} new replicas have been updated",
+                updated, desired
+            ),
+        )
+    } else if replicas > updated {
+        (
+            RolloutState::Progressing,
+            format!(
+                "Waiting for rollout to finish: {} old replicas are pending termination",
+                replicas - updated
+            ),
+        )
+    } else if available < updated {
+        (
+            RolloutState::Progressing,
+            format!(
+                "Waiting for rollout to finish: {} of {} updated replicas are available",
+                available, updated
+            ),
+        )
+    } else {
+        (
+            RolloutState::Complete,
+            format!("Deployment {} successfully rolled out", name),
+        )
+    };
+
+    RolloutStatus {
+        name,
+        state,
+        message,
+        generation,
+        observed_generation: status.observed_generation,
+        replicas: desired,
+        updated_replicas: updated,
+        ready_replicas: status.ready_replicas.unwrap_or_default(),
+        available_replicas: available,
+        unavailable_replicas: status.unavailable_replicas.unwrap_or_default(),
+        paused: spec.and_then(|spec| spec.paused).unwrap_or_default(),
+        conditions,
+    }
+}
+
+/// Computes the rollout status of a StatefulSet following the rules of
+/// `kubectl rollout status`.
+pub fn statefulset_rollout_status(statefulset: &StatefulSet) -> RolloutStatus {
+    let name = statefulset.metadata.name.clone().unwrap_or_default();
+    let spec = statefulset.spec.as_ref();
+    let status = statefulset.status.clone().unwrap_or_default();
+    let generation = statefulset.metadata.generation;
+    let desired = spec.and_then(|spec| spec.replicas).unwrap_or(1);
+    let updated = status.updated_replicas.unwrap_or_default();
+    let ready = status.ready_replicas.unwrap_or_default();
+    let available = status.available_replicas.unwrap_or_default();
+    let strategy = spec.and_then(|spec| spec.update_strategy.as_ref());
+    let partition = strategy
+        .and_then(|strategy| strategy.rolling_update.as_ref())
+        .and_then(|rolling_update| rolling_update.partition)
+        .unwrap_or_default();
+    let on_delete = strategy.and_then(|strategy| strategy.type_.as_deref()) == Some("OnDelete");
+
+    let (state, message) = if on_delete {
+        (
+            RolloutState::Complete,
+            format!("StatefulSet {} uses the OnDelete update strategy", name),
+        )
+    } else if generation > status.observed_generation {
+        (
+            RolloutState::Progressing,
+            "Waiting for statefulset spec update to be observed".to_owned(),
+        )
+    } else if ready < desired {
+        (
+            RolloutState::Progressing,
+            format!("Waiting for {} pods to be ready", desired - ready),
+        )
+    } else if partition > 0 && updated < desired - partition {
+        (
+            RolloutState::Progressing,
+            format!(
+                "Waiting for partitioned roll out to finish: {} out of {} new pods have been updated",
+                updated,
+                desired - partition
+            ),
+        )
+    } else if partition == 0 && status.update_revision != status.current_revision {
+        (
+            RolloutState::Progressing,
+            format!(
+                "Waiting for statefulset rolling update to complete {} pods at revision {}",
+                updated,
+                status.update_revision.as_deref().unwrap_or_default()
+            ),
+        )
+    } else {
+        (
+            RolloutState::Complete,
+            format!("StatefulSet {} successfully rolled out", name),
+        )
+    };
+
+    RolloutStatus {
+        name,
+        state,
+        message,
+        generation,
+        observed_generation: status.observed_generation,
+        replicas: desired,
+        updated_replicas: updated,
+        ready_replicas: ready,
+        available_replicas: available,
+        unavailable_replicas: (desired - available).max(0),
+        paused: false,
+        conditions: status
+            .conditions
+            .iter()
+            .flatten()
+            .map(|condition| ConditionSummary {
+                type_: condition.type_.clone(),
+                status: condition.status.clone(),
+                reason: condition.reason.clone(),
+                message: condition.message.clone(),
+                last_update_time: condition
+                    .last_transition_time
+                    .as_ref()
+                    .map(|time| time.0.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            })
+            .collect(),
+    }
+}
+
+/// Computes the rollout status of a DaemonSet following the rules of
+/// `kubectl rollout status`.
+pub fn daemonset_rollout_status(daemonset: &DaemonSet) -> RolloutStatus {
+    let name = daemonset.metadata.name.clone().unwrap_or_default();
+    let status = daemonset.status.clone().unwrap_or_default();
+    let generation = daemonset.metadata.generation;
+    let desired = status.desired_number_scheduled;
+    let updated = status.updated_number_scheduled.unwrap_or_default();
+    let available = status.number_available.unwrap_or_default();
+
+    let (state, message) = if generation > status.observed_generation {
+        (
+            RolloutState::Progressing,
+            "Waiting for daemon set spec update to be observed".to_owned(),
+        )
+    } else if updated < desired {
+        (
+            RolloutState::Progressing,
+            format!(
+                "Waiting for daemon set rollout to finish: {} out of {} new pods have been updated",
+                updated, desired
+            ),
+        )
+    } else if available < desired {
+        (
+            RolloutState::Progressing,
+            format!(
+                "Waiting for daemon set rollout to finish: {} of {} updated pods are available",
+                available, desired
+            ),
+        )
+    } else {
+        (
+            RolloutState::Complete,
+            format!("DaemonSet {} successfully rolled out", name),
+        )
+    };
+
+    RolloutStatus {
+        name,
+        state,
+        message,
+        generation,
+        observed_generation: status.observed_generation,
+        replicas: desired,
+        updated_replicas: updated,
+        ready_replicas: status.number_ready,
+        available_replicas: available,
+        unavailable_replicas: status.number_unavailable.unwrap_or_default(),
+        paused: false,
+        conditions: status
+            .conditions
+            .iter()
+            .flatten()
+            .map(|condition| ConditionSummary {
+                type_: condition.type_.clone(),
+                status: condition.status.clone(),
+                reason: condition.reason.clone(),
+                message: condition.message.clone(),
+                last_update_time: condition
+                    .last_transition_time
+                    .as_ref()
+                    .map(|time| time.0.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn deployment(spec: Value, status: Value) -> Deployment {
+        serde_json::from_value(json!({
+            "metadata": { "name": "web", "generation": 2 },
+            "spec": spec,
+            "status": status,
+        }))
+        .unwrap()
+    }
+
+    fn statefulset(spec: Value, status: Value) -> StatefulSet {
+        serde_json::from_value(json!({
+            "metadata": { "name": "db", "generation": 2 },
+            "spec": spec,
+            "status": status,
+        }))
+        .unwrap()
+    }
+
+    fn deployment_spec(replicas: i32) -> Value {
+        json!({
+            "replicas": replicas,
+            "selector": {},
+            "template": {},
+        })
+    }
+
+    fn statefulset_spec(replicas: i32, update_strategy: Value) -> Value {
+        json!({
+            "replicas": replicas,
+            "selector": {},
+            "serviceName": "db",
+            "template": {},
+            "updateStrategy": update_strategy,
+        })
+    }
+
+    #[test]
+    fn deployment_is_complete_once_all_replicas_are_updated_and_available() {
+        let status = rollout_status(&deployment(
+            deployment_spec(3),
+            json!({
+                "observedGeneration": 2,
+                "replicas": 3,
+                "updatedReplicas": 3,
+                "availableReplicas": 3,
+            }),
+        ));
+        assert_eq!(status.state, RolloutState::Complete);
+        assert_eq!(status.replicas, 3);
+    }
+
+    #[test]
+    fn deployment_waits_for_the_spec_to_be_observed() {
+        let status = rollout_status(&deployment(
+            deployment_spec(3),
+            json!({
+                "observedGeneration": 1,
+                "replicas": 3,
+                "updatedReplicas": 3,
+                "availableReplicas": 3,
+            }),
+        ));
+        assert_eq!(status.state, RolloutState::Progressing);
+        assert!(status.message.contains("observed"));
+    }
+
+    #[test]
+    fn deployment_waits_for_old_replicas_to_terminate() {
+        let status = rollout_status(&deployment(
+            deployment_spec(2),
+            json!({
+                "observedGeneration": 2,
+                "replicas": 3,
+                "updatedReplicas": 2,
+                "availableReplicas": 2,
+            }),
+        ));
+        assert_eq!(status.state, RolloutState::Progressing);
+        assert!(status.message.contains("1 old replicas"));
+    }
+
+    #[test]
+    fn deployment_fails_past_its_progress_deadline() {
+        let status = rollout_status(&deployment(
+            deployment_spec(3),
+            json!({
+                "observedGeneration": 2,
+                "replicas": 3,
+                "updatedReplicas": 1,
+                "conditions": [{
+                    "type": "Progressing",
+                    "status": "False",
+                    "reason": "ProgressDeadlineExceeded",
+                }],
+            }),
+        ));
+        assert_eq!(status.state, RolloutState::Failed);
+        assert_eq!(status.conditions.len(), 1);
+    }
+
+    #[test]
+    fn deployment_replicas_default_to_one() {
+        let status = rollout_status(&deployment(
+            json!({ "selector": {}, "template": {} }),
+            json!({ "observedGeneration": 2 }),
+        ));
+        assert_eq!(status.state, RolloutState::Progressing);
+        assert_eq!(status.replicas, 1);
+    }
+
+    #[test]
+    fn statefulset_with_on_delete_strategy_is_always_complete() {
+        let status = statefulset_rollout_status(&statefulset(
+            statefulset_spec(3, json!({ "type": "OnDelete" })),
+            json!({ "replicas": 3, "readyReplicas": 0 }),
+        ));
+        assert_eq!(status.state, RolloutState::Complete);
+    }
+
+    #[test]
+    fn statefulset_waits_for_ready_pods() {
+        let status = statefulset_rollout_status(&statefulset(
+            statefulset_spec(3, json!({ "type": "RollingUpdate" })),
+            json!({ "observedGeneration": 2, "replicas": 3, "readyReplicas": 1 }),
+        ));
+        assert_eq!(status.state, RolloutState::Progressing);
+        assert!(status.message.contains("2 pods"));
+    }
+
+    #[test]
+    fn statefulset_partition_only_waits_for_pods_above_it() {
+        let spec = statefulset_spec(
+            4,
+            json!({ "type": "RollingUpdate", "rollingUpdate": { "partition": 2 } }),
+        );
+        let progressing = statefulset_rollout_status(&statefulset(
+            spec.clone(),
+            json!({
+                "observedGeneration": 2,
+                "replicas": 4,
+                "readyReplicas": 4,
+                "updatedReplicas": 1,
+                "currentRevision": "db-1",
+                "updateRevision": "db-2",
+            }),
+        ));
+        assert_eq!(progressing.state, RolloutState::Progressing);
+        let complete = statefulset_rollout_status(&statefulset(
+            spec,
+            json!({
+                "observedGeneration": 2,
+                "replicas": 4,
+                "readyReplicas": 4,
+                "updatedReplicas": 2,
+                "currentRevision": "db-1",
+                "updateRevision": "db-2",
+            }),
+        ));
+        assert_eq!(complete.state, RolloutState::Complete);
+    }
+
+    #[test]
+    fn statefulset_waits_until_the_update_revision_is_current() {
+        let status = statefulset_rollout_status(&statefulset(
+            statefulset_spec(2, json!({ "type": "RollingUpdate" })),
+            json!({
+                "observedGeneration": 2,
+                "replicas": 2,
+                "readyReplicas": 2,
+                "updatedReplicas": 1,
+                "currentRevision": "db-1",
+                "updateRevision": "db-2",
+            }),
+        ));
+        assert_eq!(status.state, RolloutState::Progressing);
+        assert!(status.message.contains("db-2"));
+    }
+
+    #[tokio::test]
+    async fn until_settled_times_out_without_any_status() {
+        let statuses = until_settled(
+            stream::pending(),
+            "web".to_owned(),
+            Duration::from_millis(10),
+        );
+        let statuses: Vec<RolloutStatus> = statuses.collect().await;
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].state, RolloutState::TimedOut);
+        assert_eq!(statuses[0].name, "web");
+    }
+
+    #[tokio::test]
+    async fn until_settled_ends_after_a_settled_status() {
+        let mut complete = timed_out("web".to_owned(), None);
+        complete.state = RolloutState::Complete;
+        let mut progressing = complete.clone();
+        progressing.state = RolloutState::Progressing;
+        let statuses = until_settled(
+            stream::iter([progressing, complete.clone(), complete]).chain(stream::pending()),
+            "web".to_owned(),
+            Duration::from_secs(60),
+        );
+        let states: Vec<RolloutState> = statuses.map(|status| status.state).collect().await;
+        assert_eq!(states, [RolloutState::Progressing, RolloutState::Complete]);
+    }
+
+    #[test]
+    fn with_tag_replaces_the_tag() {
+        assert_eq!(with_tag("nginx:1.24", "1.25"), "nginx:1.25");
+        assert_eq!(with_tag("nginx", "1.25"), "nginx:1.25");
+        assert_eq!(
+            with_tag("library/nginx:latest", "1.25"),
+            "library/nginx:1.25"
+        );
+    }
+
+    #[test]
+    fn with_tag_keeps_a_registry_port() {
+        assert_eq!(
+            with_tag("registry.local:5000/team/app", "v2"),
+            "registry.local:5000/team/app:v2"
+        );
+        assert_eq!(
+            with_tag("registry.local:5000/team/app:v1", "v2"),
+            "registry.local:5000/team/app:v2"
+        );
+    }
+
+    #[test]
+    fn with_tag_drops_a_digest() {
+        let digest = "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+        assert_eq!(with_tag(&format!("app@{}", digest), "v2"), "app:v2");
+        assert_eq!(
+            with_tag(&format!("registry.local:5000/app:v1@{}", digest), "v2"),
+            "registry.local:5000/app:v2"
+        );
+    }
+}
@@ -0,0 +1,641 @@
+use crate::models::template::{
+    DocumentError, FieldError, RenderParams, RenderedTemplate, TemplateInfo, TemplateSource,
+    TemplateUpload,
+};
+use crate::services::{deployment_service, resource_service};
+use crate::{Error, Result};
+use anyhow::anyhow;
+use futures::StreamExt;
+use handlebars::Handlebars;
+use jsonschema::JSONSchema;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::autoscaling::v2::HorizontalPodAutoscaler;
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use k8s_openapi::api::core::v1::{
+    ConfigMap, Namespace, PersistentVolumeClaim, Pod, Secret, Service, ServiceAccount,
+};
+use k8s_openapi::api::networking::v1::{Ingress, NetworkPolicy};
+use k8s_openapi::api::policy::v1::PodDisruptionBudget;
+use k8s_openapi::api::rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding};
+use kube::api::{Api, DeleteParams, Patch, PatchParams, Preconditions};
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::{Client, ResourceExt};
+use log::*;
+use notify::{RecursiveMode, Watcher};
+use rust_embed::RustEmbed;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+#[derive(RustEmbed)]
+#[folder = "src/web/templates/"]
+struct TemplateDirectory;
+
+const TEMPLATE_SUFFIX: &str = ".yaml.hbs";
+const CATALOG_SUFFIX: &str = ".catalog.json";
+
+/// Directory of templates layered over the embedded ones, reloaded on change
+const TEMPLATE_DIR_ENV: &str = "KUBE_SPHERE_TEMPLATE_DIR";
+/// Namespace of template ConfigMaps, the client's default namespace if unset
+const TEMPLATE_NAMESPACE_ENV: &str = "KUBE_SPHERE_TEMPLATE_NAMESPACE";
+/// Label naming the template a ConfigMap holds
+const TEMPLATE_LABEL: &str = "kube-sphere.io/template";
+const CONFIGMAP_TEMPLATE_KEY: &str = "template.yaml.hbs";
+const CONFIGMAP_CATALOG_KEY: &str = "catalog.json";
+
+/// Contents of the sidecar `<name>.catalog.json` of a template
+#[derive(Deserialize, Default)]
+struct CatalogEntry {
+    description: Option<String>,
+    version: Option<String>,
+    schema: Option<serde_json::Value>,
+}
+
+#[derive(Clone)]
+struct StoredTemplate {
+    template: String,
+    catalog: Option<String>,
+    source: TemplateSource,
+}
+
+/// Templates from the configured directory and from ConfigMaps. ConfigMaps
+/// shadow the directory, which shadows the embedded templates.
+static DIRECTORY_TEMPLATES: RwLock<BTreeMap<String, StoredTemplate>> = RwLock::new(BTreeMap::new());
+static CONFIGMAP_TEMPLATES: RwLock<BTreeMap<String, StoredTemplate>> = RwLock::new(BTreeMap::new());
+
+/// Lists every template with its description and values schema.
+pub fn catalog() -> Result<Vec<TemplateInfo>> {
+    let mut names = BTreeSet::new();
+    for file_name in TemplateDirectory::iter() {
+        if let Some(name) = file_name.strip_suffix(TEMPLATE_SUFFIX) {
+            names.insert(name.to_owned());
+        }
+    }
+    names.extend(read(&DIRECTORY_TEMPLATES).keys().cloned());
+    names.extend(read(&CONFIGMAP_TEMPLATES).keys().cloned());
+    names.iter().map(|name| describe(name)).collect()
+}
+
+pub fn describe(name: &str) -> Result<TemplateInfo> {
+    let stored = lookup(name)?;
+    let entry = catalog_entry(name, &stored)?;
+    Ok(TemplateInfo {
+        name: name.to_owned(),
+        description: entry.description,
+        version: entry.version,
+        schema: entry.schema,
+        source: stored.source,
+    })
+}
+
+/// Checks `values` against the schema of a template. Every mismatch is
+/// reported, an empty list means the values are valid.
+pub fn validate(name: &str, values: &serde_json::Value) -> Result<Vec<FieldError>> {
+    let Some(schema) = describe(name)?.schema else {
+        return Ok(Vec::new());
+    };
+    let schema = JSONSchema::compile(&schema)
+        .map_err(|e| Error::General(anyhow!("Invalid schema of template {}: {}", name, e)))?;
+    let errors = match schema.validate(values) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .map(|e| FieldError {
+                field: e.instance_path.to_string(),
+                message: e.to_string(),
+            })
+            .collect(),
+    };
+    Ok(errors)
+}
+
+/// Renders a template with `values` after validating them against its
+/// schema.
+pub(crate) fn render(name: &str, values: &serde_json::Value) -> Result<String> {
+    let errors = validate(name, values)?;
+    if !errors.is_empty() {
+        return Err(Error::InvalidValues(errors));
+    }
+
+    let mut handlebars = Handlebars::new();
+    let template_str = lookup(name)?.template;
+    // values and rendered manifests can carry credentials, never log them
+    debug!("Rendering template {}", name);
+    handlebars
+        .register_template_string(name, template_str)
+        .map_err(|e| Error::General(e.into()))?;
+
+    let rendered = handlebars
+        .render(name, values)
+        .map_err(|e| Error::General(e.into()))?;
+    Ok(rendered)
+}
+
+/// Renders a template without applying it and checks every document the
+/// way the API server would read it, as far as k8s-openapi knows the kind.
+pub fn preview(
+    name: &str,
+    values: &serde_json::Value,
+    params: &RenderParams,
+) -> Result<RenderedTemplate> {
+    let rendered = render(name, values)?;
+    let container = deployment_service::documents(rendered.as_bytes())?;
+    let mut errors = Vec::new();
+    for (index, doc) in container.iter().enumerate() {
+        if doc.is_null() {
+            continue;
+        }
+        if let Err(message) = check_document(doc) {
+            errors.push(DocumentError {
+                index,
+                kind: doc["kind"].as_str().unwrap_or_default().to_owned(),
+                name: doc["metadata"]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned(),
+                message,
+            });
+        }
+    }
+
+    let (manifest, documents) = match params.format.as_deref().unwrap_or("yaml") {
+        "yaml" => (Some(rendered), None),
+        "json" => {
+            let documents = container
+                .iter()
+                .filter(|doc| !doc.is_null())
+                .map(serde_json::to_value)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            (None, Some(documents))
+        }
+        other => {
+            return Err(Error::BadRequest(format!(
+                "Unsupported format {}, expected yaml or json",
+                other
+            )))
+        }
+    };
+    Ok(RenderedTemplate {
+        manifest,
+        documents,
+        errors,
+    })
+}
+
+type DocumentCheck = fn(&serde_yaml::Value) -> Option<std::result::Result<(), String>>;
+
+/// Built-in kinds rendered documents are checked against. Other kinds,
+/// custom resources among them, are only checked for the common fields.
+const DOCUMENT_CHECKS: &[DocumentCheck] = &[
+    check_as::<Namespace>,
+    check_as::<ServiceAccount>,
+    check_as::<Role>,
+    check_as::<RoleBinding>,
+    check_as::<ClusterRole>,
+    check_as::<ClusterRoleBinding>,
+    check_as::<ConfigMap>,
+    check_as::<Secret>,
+    check_as::<PersistentVolumeClaim>,
+    check_as::<Service>,
+    check_as::<Pod>,
+    check_as::<Deployment>,
+    check_as::<StatefulSet>,
+    check_as::<DaemonSet>,
+    check_as::<Job>,
+    check_as::<CronJob>,
+    check_as::<HorizontalPodAutoscaler>,
+    check_as::<PodDisruptionBudget>,
+    check_as::<Ingress>,
+    check_as::<NetworkPolicy>,
+];
+
+fn check_document(doc: &serde_yaml::Value) -> std::result::Result<(), String> {
+    for field in ["apiVersion", "kind"] {
+        if doc[field].as_str().unwrap_or_default().is_empty() {
+            return Err(format!("Missing '{}' field", field));
+        }
+    }
+    if doc["metadata"]["name"]
+        .as_str()
+        .unwrap_or_default()
+        .is_empty()
+    {
+        return Err("Missing 'metadata.name' field".to_owned());
+    }
+    DOCUMENT_CHECKS
+        .iter()
+        .find_map(|check| check(doc))
+        .unwrap_or(Ok(()))
+}
+
+/// Deserialises `doc` as `K` if it is a `K`.
+fn check_as<K: k8s_openapi::Resource + DeserializeOwned>(
+    doc: &serde_yaml::Value,
+) -> Option<std::result::Result<(), String>> {
+    if doc["apiVersion"].as_str() != Some(K::API_VERSION) || doc["kind"].as_str() != Some(K::KIND) {
+        return None;
+    }
+    Some(
+        serde_yaml::from_value::<K>(doc.clone())
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+    )
+}
+
+fn catalog_entry(name: &str, stored: &StoredTemplate) -> Result<CatalogEntry> {
+    match &stored.catalog {
+        Some(catalog) => serde_json::from_str(catalog).map_err(|e| {
+            Error::General(anyhow!("Invalid catalog entry of template {}: {}", name, e))
+        }),
+        None => Ok(CatalogEntry::default()),
+    }
+}
+
+fn lookup(name: &str) -> Result<StoredTemplate> {
+    if let Some(stored) = read(&CONFIGMAP_TEMPLATES).get(name) {
+        return Ok(stored.clone());
+    }
+    if let Some(stored) = read(&DIRECTORY_TEMPLATES).get(name) {
+        return Ok(stored.clone());
+    }
+    let template_file = TemplateDirectory::get(&format!("{}{}", name, TEMPLATE_SUFFIX))
+        .ok_or(Error::TemplateNotFound)?;
+    let template =
+        String::from_utf8(template_file.data.into_owned()).map_err(|e| Error::General(e.into()))?;
+    let catalog = TemplateDirectory::get(&format!("{}{}", name, CATALOG_SUFFIX))
+        .map(|file| String::from_utf8_lossy(&file.data).into_owned());
+    Ok(StoredTemplate {
+        template,
+        catalog,
+        source: TemplateSource::Embedded,
+    })
+}
+
+fn read(
+    templates: &RwLock<BTreeMap<String, StoredTemplate>>,
+) -> std::sync::RwLockReadGuard<'_, BTreeMap<String, StoredTemplate>> {
+    templates.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write(
+    templates: &RwLock<BTreeMap<String, StoredTemplate>>,
+) -> std::sync::RwLockWriteGuard<'_, BTreeMap<String, StoredTemplate>> {
+    templates.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Starts loading templates from the configured directory and from
+/// ConfigMaps, and keeps both up to date in the background.
+pub async fn watch_sources(client: Arc<Client>) {
+    if let Ok(dir) = std::env::var(TEMPLATE_DIR_ENV) {
+        if let Err(e) = watch_directory(PathBuf::from(&dir)) {
+            error!("Cannot watch template directory {}: {}", dir, e);
+        }
+    }
+
+    let namespace = template_namespace(&client);
+    let configmap_api: Api<ConfigMap> = Api::namespaced(client.as_ref().clone(), &namespace);
+    info!("Watching template ConfigMaps in namespace {}", namespace);
+    tokio::spawn(async move {
+        let config = watcher::Config::default().labels(TEMPLATE_LABEL);
+        let mut events = watcher(configmap_api, config)
+            .backoff(watcher::default_backoff())
+            .boxed();
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(watcher::Event::Applied(configmap)) => {
+                    if let Some((name, stored)) = configmap_template(&configmap) {
+                        write(&CONFIGMAP_TEMPLATES).insert(name, stored);
+                    }
+                }
+                Ok(watcher::Event::Deleted(configmap)) => {
+                    if let Some(name) = configmap.labels().get(TEMPLATE_LABEL) {
+                        write(&CONFIGMAP_TEMPLATES).remove(name);
+                    }
+                }
+                Ok(watcher::Event::Restarted(configmaps)) => {
+                    *write(&CONFIGMAP_TEMPLATES) =
+                        configmaps.iter().filter_map(configmap_template).collect();
+                }
+                Err(e) => warn!("Watching template ConfigMaps failed: {}", e),
+            }
+        }
+    });
+}
+
+fn watch_directory(dir: PathBuf) -> Result<()> {
+    load_directory(&dir)?;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut dir_watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if let Err(e) = tx.send(event) {
+                warn!("Template directory watcher stopped: {}", e);
+            }
+        })
+        .map_err(|e| Error::General(e.into()))?;
+    dir_watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| Error::General(e.into()))?;
+    info!("Watching template directory {}", dir.display());
+
+    tokio::spawn(async move {
+        // the watcher stops when dropped, keep it for as long as events come
+        let _dir_watcher = dir_watcher;
+        while let Some(event) = rx.recv().await {
+            if let Err(e) = event {
+                warn!("Watching template directory failed: {}", e);
+                continue;
+            }
+            if let Err(e) = load_directory(&dir) {
+                error!("Cannot reload template directory {}: {}", dir.display(), e);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Reads every template of `dir`, replacing what was read before.
+fn load_directory(dir: &Path) -> Result<()> {
+    let mut templates = BTreeMap::new();
+    for entry in std::fs::read_dir(dir).map_err(|e| Error::General(e.into()))? {
+        let path = entry.map_err(|e| Error::General(e.into()))?.path();
+        let Some(name) = path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .and_then(|file_name| file_name.strip_suffix(TEMPLATE_SUFFIX))
+        else {
+            continue;
+        };
+        let template = match std::fs::read_to_string(&path) {
+            Ok(template) => template,
+            Err(e) => {
+                warn!("Cannot read template {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let catalog = std::fs::read_to_string(dir.join(format!("{}{}", name, CATALOG_SUFFIX))).ok();
+        templates.insert(
+            name.to_owned(),
+            StoredTemplate {
+                template,
+                catalog,
+                source: TemplateSource::Directory,
+            },
+        );
+    }
+    info!(
+        "Loaded {} templates from {}",
+        templates.len(),
+        dir.display()
+    );
+    *write(&DIRECTORY_TEMPLATES) = templates;
+    Ok(())
+}
+
+fn configmap_template(configmap: &ConfigMap) -> Option<(String, StoredTemplate)> {
+    let name = configmap.labels().get(TEMPLATE_LABEL)?.clone();
+    let data = configmap.data.as_ref()?;
+    let Some(template) = data.get(CONFIGMAP_TEMPLATE_KEY) else {
+        warn!(
+            "ConfigMap {} holds no {}",
+            configmap.name_any(),
+            CONFIGMAP_TEMPLATE_KEY
+        );
+        return None;
+    };
+    let stored = StoredTemplate {
+        template: template.clone(),
+        catalog: data.get(CONFIGMAP_CATALOG_KEY).cloned(),
+        source: TemplateSource::ConfigMap,
+    };
+    Some((name, stored))
+}
+
+/// Uploads a template, or updates an uploaded one, as a ConfigMap in the
+/// template namespace. It shadows any directory or embedded template of
+/// the same name.
+pub async fn save(client: Arc<Client>, name: &str, upload: TemplateUpload) -> Result<TemplateInfo> {
+    check_name(name)?;
+    Handlebars::new()
+        .register_template_string(name, &upload.template)
+        .map_err(|e| Error::BadRequest(format!("Invalid template: {}", e)))?;
+    let catalog = match upload.catalog {
+        Some(catalog) => {
+            let entry: CatalogEntry = serde_json::from_value(catalog.clone())
+                .map_err(|e| Error::BadRequest(format!("Invalid catalog entry: {}", e)))?;
+            if let Some(schema) = &entry.schema {
+                JSONSchema::compile(schema)
+                    .map_err(|e| Error::BadRequest(format!("Invalid schema: {}", e)))?;
+            }
+            Some(serde_json::to_string_pretty(&catalog)?)
+        }
+        None => None,
+    };
+
+    let stored = StoredTemplate {
+        template: upload.template,
+        catalog,
+        source: TemplateSource::ConfigMap,
+    };
+    let mut data = BTreeMap::from([(CONFIGMAP_TEMPLATE_KEY.to_owned(), stored.template.clone())]);
+    if let Some(catalog) = &stored.catalog {
+        data.insert(CONFIGMAP_CATALOG_KEY.to_owned(), catalog.clone());
+    }
+    let mut configmap = ConfigMap {
+        data: Some(data),
+        ..ConfigMap::default()
+    };
+    configmap.metadata.name = Some(configmap_name(name));
+    configmap.metadata.labels = Some(BTreeMap::from([(
+        TEMPLATE_LABEL.to_owned(),
+        name.to_owned(),
+    )]));
+
+    let namespace = template_namespace(&client);
+    let configmap_api: Api<ConfigMap> = Api::namespaced(client.as_ref().clone(), &namespace);
+    // never take over a ConfigMap that is not one of ours, nor fields of
+    // one that another manager owns
+    if let Some(existing) = configmap_api.get_opt(&configmap_name(name)).await? {
+        check_owned(&existing, name, &namespace)?;
+    }
+    let pp = PatchParams::apply(resource_service::FIELD_MANAGER);
+    match configmap_api
+        .patch(&configmap_name(name), &pp, &Patch::Apply(&configmap))
+        .await
+    {
+        Ok(_) => {}
+        Err(kube::Error::Api(ae)) if ae.code == 409 => {
+            return Err(Error::ResourceAlreadyExists(anyhow!(
+                "Template {} is also managed elsewhere: {}",
+                name,
+                ae.message
+            )))
+        }
+        Err(e) => return Err(e.into()),
+    }
+    info!("Template {} saved in namespace {}", name, namespace);
+    // the watcher catches up too, but callers expect to use it right away
+    write(&CONFIGMAP_TEMPLATES).insert(name.to_owned(), stored);
+    describe(name)
+}
+
+/// Deletes an uploaded template. A directory or embedded template of the
+/// same name takes its place again.
+pub async fn remove(client: Arc<Client>, name: &str) -> Result<String> {
+    check_name(name)?;
+    let namespace = template_namespace(&client);
+    let configmap_api: Api<ConfigMap> = Api::namespaced(client.as_ref().clone(), &namespace);
+    let not_uploaded = || Error::ResourceNotFound(format!("Template {} was not uploaded", name));
+    // never delete a ConfigMap that is not one of ours, the precondition
+    // makes sure it is still the one checked
+    let existing = configmap_api
+        .get_opt(&configmap_name(name))
+        .await?
+        .ok_or_else(not_uploaded)?;
+    check_owned(&existing, name, &namespace)?;
+    let dp = DeleteParams {
+        preconditions: Some(Preconditions {
+            uid: existing.uid(),
+            resource_version: None,
+        }),
+        ..DeleteParams::default()
+    };
+    match configmap_api.delete(&configmap_name(name), &dp).await {
+        Ok(_) => {}
+        Err(kube::Error::Api(ae)) if ae.code == 404 => return Err(not_uploaded()),
+        Err(e) => return Err(e.into()),
+    }
+    write(&CONFIGMAP_TEMPLATES).remove(name);
+    info!("Template {} deleted from namespace {}", name, namespace);
+    Ok("OK".to_owned())
+}
+
+/// Template names become part of a ConfigMap name and a label value.
+fn check_name(name: &str) -> Result<()> {
+    let valid_name = !name.is_empty()
+        && name.len() <= 63
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid_name {
+        return Err(Error::BadRequest(format!(
+            "Invalid template name {}, expected lowercase letters, digits and '-'",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Refuses a ConfigMap that is not labelled as the template `name`.
+fn check_owned(configmap: &ConfigMap, name: &str, namespace: &str) -> Result<()> {
+    if configmap.labels().get(TEMPLATE_LABEL).map(String::as_str) != Some(name) {
+        return Err(Error::ResourceAlreadyExists(anyhow!(
+            "ConfigMap {} in namespace {} is not a template of {}",
+            configmap_name(name),
+            namespace,
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn configmap_name(name: &str) -> String {
+    format!("kube-sphere-template-{}", name)
+}
+
+fn template_namespace(client: &Client) -> String {
+    std::env::var(TEMPLATE_NAMESPACE_ENV).unwrap_or_else(|_| client.default_namespace().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(yaml: &str) -> serde_yaml::Value {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    const DEPLOYMENT: &str = "\
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+spec:
+  replicas: 2
+  selector:
+    matchLabels:
+      app: web
+  template:
+    metadata:
+      labels:
+        app: web
+    spec:
+      containers:
+        - name: web
+          image: nginx
+";
+
+    #[test]
+    fn documents_need_api_version_kind_and_name() {
+        assert_eq!(
+            check_document(&document("kind: Service\nmetadata:\n  name: web\n")),
+            Err("Missing 'apiVersion' field".to_owned())
+        );
+        assert_eq!(
+            check_document(&document("apiVersion: v1\nmetadata:\n  name: web\n")),
+            Err("Missing 'kind' field".to_owned())
+        );
+        assert_eq!(
+            check_document(&document("apiVersion: v1\nkind: Service\nmetadata: {}\n")),
+            Err("Missing 'metadata.name' field".to_owned())
+        );
+    }
+
+    #[test]
+    fn known_kinds_are_checked_against_their_type() {
+        assert_eq!(check_document(&document(DEPLOYMENT)), Ok(()));
+        let invalid = DEPLOYMENT.replace("replicas: 2", "replicas: two");
+        assert!(check_document(&document(&invalid)).is_err());
+    }
+
+    #[test]
+    fn unknown_kinds_are_accepted() {
+        let certificate = "apiVersion: cert-manager.io/v1\nkind: Certificate\n\
+                           metadata:\n  name: tls\nspec:\n  anything: [1, 2]\n";
+        assert_eq!(check_document(&document(certificate)), Ok(()));
+    }
+
+    #[test]
+    fn template_names_must_fit_a_label() {
+        assert!(check_name("web-1").is_ok());
+        assert!(matches!(check_name(""), Err(Error::BadRequest(_))));
+        assert!(matches!(check_name("Web"), Err(Error::BadRequest(_))));
+        assert!(matches!(check_name("../web"), Err(Error::BadRequest(_))));
+        assert!(matches!(
+            check_name(&"a".repeat(64)),
+            Err(Error::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn only_configmaps_labelled_with_the_template_are_owned() {
+        let configmap = |labels: serde_json::Value| -> ConfigMap {
+            serde_json::from_value(serde_json::json!({
+                "metadata": {"name": "kube-sphere-template-web", "labels": labels}
+            }))
+            .unwrap()
+        };
+        let owned = configmap(serde_json::json!({TEMPLATE_LABEL: "web"}));
+        assert!(check_owned(&owned, "web", "default").is_ok());
+        let other = configmap(serde_json::json!({TEMPLATE_LABEL: "api"}));
+        assert!(matches!(
+            check_owned(&other, "web", "default"),
+            Err(Error::ResourceAlreadyExists(_))
+        ));
+        let unlabelled = configmap(serde_json::json!({}));
+        assert!(matches!(
+            check_owned(&unlabelled, "web", "default"),
+            Err(Error::ResourceAlreadyExists(_))
+        ));
+    }
+}
@@ -0,0 +1,66 @@
+use crate::models::template::{
+    FieldError, RenderParams, RenderedTemplate, TemplateInfo, TemplateUpload,
+};
+use crate::{services::template_service, Result};
+use axum::{
+    extract::{Json, Path, Query, State},
+    routing::{get, post},
+    Router,
+};
+use kube::Client;
+use std::sync::Arc;
+
+pub fn routes(client: Arc<Client>) -> Router {
+    Router::new()
+        .route("/templates", get(list_templates))
+        .route(
+            "/templates/:name",
+            get(get_template).put(save_template).delete(delete_template),
+        )
+        .route("/templates/:name/validate", post(validate))
+        .route("/templates/:name/render", post(render))
+        .with_state(client)
+}
+
+async fn list_templates() -> Result<Json<Vec<TemplateInfo>>> {
+    let templates = template_service::catalog()?;
+    Ok(Json(templates))
+}
+
+async fn get_template(Path(name): Path<String>) -> Result<Json<TemplateInfo>> {
+    let template = template_service::describe(&name)?;
+    Ok(Json(template))
+}
+
+async fn save_template(
+    State(client): State<Arc<Client>>,
+    Path(name): Path<String>,
+    Json(upload): Json<TemplateUpload>,
+) -> Result<Json<TemplateInfo>> {
+    let template = template_service::save(client, &name, upload).await?;
+    Ok(Json(template))
+}
+
+async fn delete_template(
+    State(client): State<Arc<Client>>,
+    Path(name): Path<String>,
+) -> Result<String> {
+    template_service::remove(client, &name).await
+}
+
+async fn validate(
+    Path(name): Path<String>,
+    Json(values): Json<serde_json::Value>,
+) -> Result<Json<Vec<FieldError>>> {
+    let errors = template_service::validate(&name, &values)?;
+    Ok(Json(errors))
+}
+
+async fn render(
+    Path(name): Path<String>,
+    Query(params): Query<RenderParams>,
+    Json(values): Json<serde_json::Value>,
+) -> Result<Json<RenderedTemplate>> {
+    let rendered = template_service::preview(&name, &values, &params)?;
+    Ok(Json(rendered))
+}
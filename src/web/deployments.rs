@@ -1,16 +1,29 @@
 //Deployment 作为 Kubernetes 中最常见和重要的资源类型之一，通常是指建立服务集合的最常见方式之一。
 //并且与其他资源类型（如 Service 和 Ingress）一起使用的。
+use crate::models::deployment::{
+    DeploymentDetail, PatchQuery, Revision, RollbackParams, RolloutStatus, RolloutStatusParams,
+    ScaleParams, SetImageParams, SetImageResult,
+};
+use crate::models::manifest::{DeleteManifestParams, ManifestParams};
+use crate::services::rollout_service::Workload;
 use crate::services::*;
 use crate::Result;
+use axum::http::{header, HeaderMap};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
 use axum::routing::post;
 use axum::{
     body::Bytes,
-    extract::{Json, Path, State},
+    extract::{Json, Path, Query, State},
     routing::get,
     Router,
 };
+use futures::StreamExt;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::batch::v1::CronJob;
 use kube::Client;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub fn routes(client: Arc<Client>) -> Router {
     Router::new()
@@ -23,7 +36,67 @@ pub fn routes(client: Arc<Client>) -> Router {
         )
         .route(
             "/namespaces/:namespace/deployments",
-            post(create_deployment_by_yaml),
+            post(create_deployment_by_yaml).delete(delete_deployment_by_yaml),
+        )
+        .route(
+            "/namespaces/:namespace/deployments/:deployment_name/uninstall",
+            post(uninstall_deployment),
+        )
+        .route(
+            "/namespaces/:namespace/deployments/:deployment_name/status",
+            get(rollout_status),
+        )
+        .route(
+            "/namespaces/:namespace/deployments/:deployment_name/history",
+            get(rollout_history),
+        )
+        .route(
+            "/namespaces/:namespace/deployments/:deployment_name/rollback",
+            post(rollback),
+        )
+        .route(
+            "/namespaces/:namespace/deployments/:deployment_name/restart",
+            post(restart::<Deployment>),
+        )
+        .route(
+            "/namespaces/:namespace/deployments/:deployment_name/pause",
+            post(pause),
+        )
+        .route(
+            "/namespaces/:namespace/deployments/:deployment_name/resume",
+            post(resume),
+        )
+        .route(
+            "/namespaces/:namespace/deployments/:deployment_name/scale",
+            post(scale::<Deployment>),
+        )
+        .route(
+            "/namespaces/:namespace/deployments/:deployment_name/image",
+            post(set_image::<Deployment>),
+        )
+        .route(
+            "/namespaces/:namespace/statefulsets/:name/image",
+            post(set_image::<StatefulSet>),
+        )
+        .route(
+            "/namespaces/:namespace/daemonsets/:name/image",
+            post(set_image::<DaemonSet>),
+        )
+        .route(
+            "/namespaces/:namespace/cronjobs/:name/image",
+            post(set_cronjob_image),
+        )
+        .route(
+            "/namespaces/:namespace/statefulsets/:name/restart",
+            post(restart::<StatefulSet>),
+        )
+        .route(
+            "/namespaces/:namespace/statefulsets/:name/scale",
+            post(scale::<StatefulSet>),
+        )
+        .route(
+            "/namespaces/:namespace/daemonsets/:name/restart",
+            post(restart::<DaemonSet>),
         )
         .with_state(client)
 }
@@ -31,32 +104,98 @@ pub fn routes(client: Arc<Client>) -> Router {
 async fn create_deployment(
     State(client): State<Arc<Client>>,
     Path((namespace, deployment_name)): Path<(String, String)>,
+    Query(params): Query<ManifestParams>,
     Json(data): Json<serde_json::Value>,
 ) -> Result<String> {
-    deployment_service::create_deployment(client, &namespace, &deployment_name, data).await
+    deployment_service::create_deployment(client, &namespace, &deployment_name, data, params).await
 }
 
 async fn create_deployment_by_yaml(
     State(client): State<Arc<Client>>,
     Path(namespace): Path<String>,
+    Query(params): Query<ManifestParams>,
     body: Bytes,
 ) -> Result<String> {
-    deployment_service::create_deployment_by_yaml(client, &namespace, &body).await
+    deployment_service::create_deployment_by_yaml(client, &namespace, &body, params).await
 }
 
-pub async fn get_deployment(
+async fn delete_deployment_by_yaml(
     State(client): State<Arc<Client>>,
-    Path((namespace, deployment_name)): Path<(String, String)>,
+    Path(namespace): Path<String>,
+    Query(params): Query<DeleteManifestParams>,
+    body: Bytes,
 ) -> Result<String> {
-    deployment_service::get_deployment(client, &namespace, &deployment_name).await
+    deployment_service::delete_deployment_by_yaml(client, &namespace, &body, params).await
 }
 
-async fn patch_deployment(
+async fn uninstall_deployment(
     State(client): State<Arc<Client>>,
     Path((namespace, deployment_name)): Path<(String, String)>,
+    Query(params): Query<DeleteManifestParams>,
     Json(data): Json<serde_json::Value>,
 ) -> Result<String> {
-    deployment_service::patch_deployment(client, &namespace, &deployment_name, data).await
+    deployment_service::uninstall_deployment(client, &namespace, &deployment_name, data, params)
+        .await
+}
+
+pub async fn get_deployment(
+    State(client): State<Arc<Client>>,
+    Path((namespace, deployment_name)): Path<(String, String)>,
+) -> Result<Json<DeploymentDetail>> {
+    let detail = deployment_service::get_deployment(client, &namespace, &deployment_name).await?;
+    Ok(Json(detail))
+}
+
+async fn rollout_status(
+    State(client): State<Arc<Client>>,
+    Path((namespace, deployment_name)): Path<(String, String)>,
+    Query(params): Query<RolloutStatusParams>,
+) -> Result<Response> {
+    if !params.wait.unwrap_or_default() {
+        let status =
+            rollout_service::status::<Deployment>(client, &namespace, &deployment_name).await?;
+        return Ok(Json(status).into_response());
+    }
+    let timeout = params
+        .timeout_seconds
+        .map(Duration::from_secs)
+        .unwrap_or(rollout_service::DEFAULT_ROLLOUT_TIMEOUT);
+    if params.sse.unwrap_or_default() {
+        let statuses =
+            rollout_service::watch::<Deployment>(client, &namespace, &deployment_name, timeout)
+                .await?;
+        let events = statuses.map(|status| Event::default().json_data(status));
+        Ok(Sse::new(events)
+            .keep_alive(KeepAlive::default())
+            .into_response())
+    } else {
+        let status =
+            rollout_service::wait::<Deployment>(client, &namespace, &deployment_name, timeout)
+                .await?;
+        Ok(Json(status).into_response())
+    }
+}
+
+async fn patch_deployment(
+    State(client): State<Arc<Client>>,
+    Path((namespace, deployment_name)): Path<(String, String)>,
+    Query(query): Query<PatchQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Deployment>> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    let deployment = deployment_service::patch_deployment(
+        client,
+        &namespace,
+        &deployment_name,
+        query,
+        content_type,
+        &body,
+    )
+    .await?;
+    Ok(Json(deployment))
 }
 
 async fn delete_deployment(
@@ -65,3 +204,90 @@ async fn delete_deployment(
 ) -> Result<String> {
     deployment_service::delete_deployment(client, &namespace, &deployment_name).await
 }
+
+async fn rollout_history(
+    State(client): State<Arc<Client>>,
+    Path((namespace, deployment_name)): Path<(String, String)>,
+) -> Result<Json<Vec<Revision>>> {
+    let revisions = rollout_service::history(client, &namespace, &deployment_name).await?;
+    Ok(Json(revisions))
+}
+
+async fn rollback(
+    State(client): State<Arc<Client>>,
+    Path((namespace, deployment_name)): Path<(String, String)>,
+    params: Option<Json<RollbackParams>>,
+) -> Result<Json<RolloutStatus>> {
+    let params = params.map(|Json(params)| params).unwrap_or_default();
+    let status =
+        rollout_service::rollback(client, &namespace, &deployment_name, params.revision).await?;
+    Ok(Json(status))
+}
+
+async fn restart<K: Workload>(
+    State(client): State<Arc<Client>>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> Result<Json<RolloutStatus>> {
+    let status = rollout_service::restart::<K>(client, &namespace, &name).await?;
+    Ok(Json(status))
+}
+
+async fn pause(
+    State(client): State<Arc<Client>>,
+    Path((namespace, deployment_name)): Path<(String, String)>,
+) -> Result<Json<RolloutStatus>> {
+    let status = rollout_service::set_paused(client, &namespace, &deployment_name, true).await?;
+    Ok(Json(status))
+}
+
+async fn resume(
+    State(client): State<Arc<Client>>,
+    Path((namespace, deployment_name)): Path<(String, String)>,
+) -> Result<Json<RolloutStatus>> {
+    let status = rollout_service::set_paused(client, &namespace, &deployment_name, false).await?;
+    Ok(Json(status))
+}
+
+async fn scale<K: Workload>(
+    State(client): State<Arc<Client>>,
+    Path((namespace, name)): Path<(String, String)>,
+    Json(params): Json<ScaleParams>,
+) -> Result<Json<RolloutStatus>> {
+    let status = rollout_service::scale::<K>(client, &namespace, &name, params.replicas).await?;
+    Ok(Json(status))
+}
+
+async fn set_image<K: Workload>(
+    State(client): State<Arc<Client>>,
+    Path((namespace, name)): Path<(String, String)>,
+    Json(params): Json<SetImageParams>,
+) -> Result<Json<SetImageResult>> {
+    let (workload, updated) =
+        rollout_service::set_image::<K>(client.clone(), &namespace, &name, &params).await?;
+    let rollout = if params.wait.unwrap_or_default() {
+        let timeout = params
+            .timeout_seconds
+            .map(Duration::from_secs)
+            .unwrap_or(rollout_service::DEFAULT_ROLLOUT_TIMEOUT);
+        rollout_service::wait::<K>(client, &namespace, &name, timeout).await?
+    } else {
+        workload.rollout_status()
+    };
+    Ok(Json(SetImageResult {
+        updated,
+        rollout: Some(rollout),
+    }))
+}
+
+async fn set_cronjob_image(
+    State(client): State<Arc<Client>>,
+    Path((namespace, name)): Path<(String, String)>,
+    Json(params): Json<SetImageParams>,
+) -> Result<Json<SetImageResult>> {
+    let (_, updated) =
+        rollout_service::set_image::<CronJob>(client, &namespace, &name, &params).await?;
+    Ok(Json(SetImageResult {
+        updated,
+        rollout: None,
+    }))
+}
@@ -0,0 +1,40 @@
+use crate::models::event::{EventQuery, EventSummary};
+use crate::{services::event_service, Result};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::{
+    extract::{Json, Path, Query, State},
+    routing::get,
+    Router,
+};
+use futures::StreamExt;
+use kube::Client;
+use std::sync::Arc;
+
+pub fn routes(client: Arc<Client>) -> Router {
+    Router::new()
+        .route("/namespaces/:namespace/events", get(list_events))
+        .route("/namespaces/:namespace/events/watch", get(watch_events))
+        .with_state(client)
+}
+
+async fn list_events(
+    State(client): State<Arc<Client>>,
+    Path(namespace): Path<String>,
+    Query(query): Query<EventQuery>,
+) -> Result<Json<Vec<EventSummary>>> {
+    let events = event_service::list(client, &namespace, &query).await?;
+    Ok(Json(events))
+}
+
+async fn watch_events(
+    State(client): State<Arc<Client>>,
+    Path(namespace): Path<String>,
+    Query(query): Query<EventQuery>,
+) -> Result<Response> {
+    let events = event_service::watch(client, &namespace, &query).await?;
+    let events = events.map(|event| Event::default().json_data(event));
+    Ok(Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
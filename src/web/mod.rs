@@ -6,6 +6,10 @@ pub mod pods;
 pub mod namespaces;
 pub mod configmaps;
 pub mod ingress;
+pub mod events;
+pub mod resources;
+pub mod releases;
+pub mod catalog;
 
 pub(crate) async fn my_middleware<B>(
     State(_client): State<Arc<Client>>,
@@ -1,18 +1,27 @@
 use crate::models::pod;
-use crate::{services::pod_service, Result};
-use axum::extract::Query;
+use crate::{services::pod_service, Error, Result};
+use axum::body::StreamBody;
+use axum::extract::ws::WebSocketUpgrade;
+use axum::extract::{Multipart, Query};
+use axum::http::header;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
 use axum::{
     extract::{Json, Path, State},
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
+use futures::{StreamExt, TryStreamExt};
 use kube::Client;
+use log::error;
 use serde::Deserialize;
+use std::convert::Infallible;
 use std::sync::Arc;
 
 #[derive(Deserialize)]
 struct QueryParams {
     labels: Option<String>,
+    fields: Option<String>,
 }
 
 pub fn routes(client: Arc<Client>) -> Router {
@@ -21,17 +30,45 @@ pub fn routes(client: Arc<Client>) -> Router {
             "/namespaces/:namespace/pods/:pod_name",
             get(pod_info).delete(del_pod),
         )
-        .route("/namespaces/:namespace/pods/:pod_name/exec", post(execute))
+        .route(
+            "/namespaces/:namespace/pods/:pod_name/exec",
+            get(exec_terminal).post(execute),
+        )
+        .route(
+            "/namespaces/:namespace/pods/:pod_name/attach",
+            get(attach_terminal),
+        )
+        .route("/namespaces/:namespace/pods/:pod_name/debug", post(debug))
         .route("/namespaces/:namespace/pods/:pod_name/logs", get(pod_logs))
-        .route("/namespaces/:namespace/pods", get(find_pod_by_labels))
+        .route(
+            "/namespaces/:namespace/pods/:pod_name/files",
+            get(download).post(upload),
+        )
+        .route(
+            "/namespaces/:namespace/pods/:pod_name/portforward/:port",
+            get(port_forward).post(port_forward_listener),
+        )
+        .route(
+            "/namespaces/:namespace/portforwards",
+            get(port_forward_listeners),
+        )
+        .route(
+            "/namespaces/:namespace/portforwards/:id",
+            delete(close_port_forward_listener),
+        )
+        .route("/pods", get(list_all_pods))
+        .route("/namespaces/:namespace/pods", get(list_pods))
+        .route("/namespaces/:namespace/logs", get(aggregate_logs))
         .with_state(client)
 }
 
 async fn pod_info(
     State(client): State<Arc<Client>>,
     Path((namespace, pod_name)): Path<(String, String)>,
-) -> Result<String> {
-    pod_service::pod_info(client, &namespace, &pod_name).await
+    Query(params): Query<pod::PodInfoParams>,
+) -> Result<Json<pod::PodDetail>> {
+    let detail = pod_service::pod_info(client, &namespace, &pod_name, params).await?;
+    Ok(Json(detail))
 }
 
 async fn del_pod(
@@ -44,23 +81,186 @@ async fn del_pod(
 async fn pod_logs(
     State(client): State<Arc<Client>>,
     Path((namespace, pod_name)): Path<(String, String)>,
-) -> Result<String> {
-    pod_service::pod_logs(client, &namespace, &pod_name).await
+    Query(query): Query<pod::LogQuery>,
+) -> Result<Response> {
+    if !query.follow.unwrap_or_default() {
+        let logs = pod_service::pod_logs(client, &namespace, &pod_name, &query).await?;
+        return Ok(logs.into_response());
+    }
+    let lines = pod_service::pod_log_stream(client, &namespace, &pod_name, &query).await?;
+    if query.sse.unwrap_or_default() {
+        let events = lines.map_ok(|line| Event::default().data(line));
+        Ok(Sse::new(events)
+            .keep_alive(KeepAlive::default())
+            .into_response())
+    } else {
+        let chunks = lines.map_ok(|line| format!("{}\n", line));
+        Ok(StreamBody::new(chunks).into_response())
+    }
 }
 
 async fn execute(
     State(client): State<Arc<Client>>,
     Path((namespace, pod_name)): Path<(String, String)>,
     Json(exec_params): Json<pod::ExecParams>,
-) -> String {
-    pod_service::exec(client, &namespace, &pod_name, exec_params).await
+) -> Result<Json<pod::ExecResult>> {
+    let result = pod_service::exec(client, &namespace, &pod_name, exec_params).await?;
+    Ok(Json(result))
 }
 
-async fn find_pod_by_labels(
+async fn exec_terminal(
+    State(client): State<Arc<Client>>,
+    Path((namespace, pod_name)): Path<(String, String)>,
+    Query(params): Query<pod::TerminalParams>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| async move {
+        if let Err(e) =
+            pod_service::exec_terminal(client, &namespace, &pod_name, params, socket).await
+        {
+            error!("terminal in {} failed: {}", pod_name, e);
+        }
+    })
+}
+
+async fn attach_terminal(
+    State(client): State<Arc<Client>>,
+    Path((namespace, pod_name)): Path<(String, String)>,
+    Query(params): Query<pod::TerminalParams>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| async move {
+        if let Err(e) =
+            pod_service::attach_terminal(client, &namespace, &pod_name, params, socket).await
+        {
+            error!("attach to {} failed: {}", pod_name, e);
+        }
+    })
+}
+
+async fn debug(
+    State(client): State<Arc<Client>>,
+    Path((namespace, pod_name)): Path<(String, String)>,
+    params: Option<Json<pod::DebugParams>>,
+) -> Result<Json<pod::DebugSession>> {
+    let params = params.map(|Json(params)| params).unwrap_or_default();
+    let session = pod_service::debug(client, &namespace, &pod_name, params).await?;
+    Ok(Json(session))
+}
+
+async fn port_forward(
+    State(client): State<Arc<Client>>,
+    Path((namespace, pod_name, port)): Path<(String, String, u16)>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| async move {
+        if let Err(e) = pod_service::port_forward(client, &namespace, &pod_name, port, socket).await
+        {
+            error!("port-forward to {}:{} failed: {}", pod_name, port, e);
+        }
+    })
+}
+
+async fn port_forward_listener(
+    State(client): State<Arc<Client>>,
+    Path((namespace, pod_name, port)): Path<(String, String, u16)>,
+    params: Option<Json<pod::PortForwardParams>>,
+) -> Result<Json<pod::PortForwardListener>> {
+    let params = params.map(|Json(params)| params).unwrap_or_default();
+    let listener =
+        pod_service::port_forward_listener(client, &namespace, &pod_name, port, params).await?;
+    Ok(Json(listener))
+}
+
+async fn port_forward_listeners(
+    Path(namespace): Path<String>,
+) -> Json<Vec<pod::PortForwardListener>> {
+    Json(pod_service::port_forward_listeners(&namespace))
+}
+
+async fn close_port_forward_listener(
+    Path((namespace, id)): Path<(String, String)>,
+) -> Result<Json<pod::PortForwardListener>> {
+    let listener = pod_service::close_port_forward_listener(&namespace, &id)?;
+    Ok(Json(listener))
+}
+
+async fn download(
+    State(client): State<Arc<Client>>,
+    Path((namespace, pod_name)): Path<(String, String)>,
+    Query(params): Query<pod::CopyParams>,
+) -> Result<Response> {
+    let (file_name, archive) = pod_service::download(client, &namespace, &pod_name, params).await?;
+    let headers = [
+        (header::CONTENT_TYPE, "application/x-tar".to_owned()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", file_name),
+        ),
+    ];
+    Ok((headers, StreamBody::new(archive)).into_response())
+}
+
+async fn upload(
+    State(client): State<Arc<Client>>,
+    Path((namespace, pod_name)): Path<(String, String)>,
+    Query(params): Query<pod::CopyParams>,
+    mut multipart: Multipart,
+) -> Result<Json<Vec<String>>> {
+    let mut files = Vec::new();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| Error::BadRequest(e.to_string()))?
+    {
+        let Some(file_name) = field.file_name().map(str::to_owned) else {
+            continue;
+        };
+        let data = field
+            .bytes()
+            .await
+            .map_err(|e| Error::BadRequest(e.to_string()))?;
+        files.push((file_name, data));
+    }
+    if files.is_empty() {
+        return Err(Error::BadRequest("No files were uploaded".to_owned()));
+    }
+    let uploaded = pod_service::upload(client, &namespace, &pod_name, params, files).await?;
+    Ok(Json(uploaded))
+}
+
+async fn list_pods(
     State(client): State<Arc<Client>>,
     Path(namespace): Path<String>,
     Query(query_params): Query<QueryParams>,
-) -> Result<String> {
+) -> Result<Json<Vec<pod::PodSummary>>> {
     let labels = query_params.labels.unwrap_or_default();
-    pod_service::find_pod_by_labels(client, &namespace, &labels).await
+    let fields = query_params.fields.unwrap_or_default();
+    let pods = pod_service::list_pods(client, Some(&namespace), &labels, &fields).await?;
+    Ok(Json(pods))
+}
+
+async fn list_all_pods(
+    State(client): State<Arc<Client>>,
+    Query(query_params): Query<QueryParams>,
+) -> Result<Json<Vec<pod::PodSummary>>> {
+    let labels = query_params.labels.unwrap_or_default();
+    let fields = query_params.fields.unwrap_or_default();
+    let pods = pod_service::list_pods(client, None, &labels, &fields).await?;
+    Ok(Json(pods))
+}
+
+async fn aggregate_logs(
+    State(client): State<Arc<Client>>,
+    Path(namespace): Path<String>,
+    Query(query): Query<pod::AggregateLogQuery>,
+) -> Result<Response> {
+    let lines = pod_service::aggregate_logs(client, &namespace, query).await?;
+    let events = lines.map(|line| {
+        let data = format!("[{}/{}] {}", line.pod, line.container, line.line);
+        Ok::<_, Infallible>(Event::default().data(data))
+    });
+    Ok(Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response())
 }
@@ -0,0 +1,94 @@
+use crate::models::manifest::DeleteResult;
+use crate::models::release::{
+    InstallParams, Release, ReleaseReport, ReleaseRollbackParams, ReleaseSummary, UpgradeParams,
+};
+use crate::{services::release_service, Result};
+use axum::{
+    extract::{Json, Path, State},
+    routing::{get, post},
+    Router,
+};
+use kube::Client;
+use std::sync::Arc;
+
+pub fn routes(client: Arc<Client>) -> Router {
+    Router::new()
+        .route(
+            "/namespaces/:namespace/releases",
+            get(list_releases).post(install),
+        )
+        .route(
+            "/namespaces/:namespace/releases/:name",
+            get(get_release).put(upgrade).delete(uninstall),
+        )
+        .route(
+            "/namespaces/:namespace/releases/:name/history",
+            get(history),
+        )
+        .route(
+            "/namespaces/:namespace/releases/:name/rollback",
+            post(rollback),
+        )
+        .with_state(client)
+}
+
+async fn list_releases(
+    State(client): State<Arc<Client>>,
+    Path(namespace): Path<String>,
+) -> Result<Json<Vec<ReleaseSummary>>> {
+    let releases = release_service::list(client, &namespace).await?;
+    Ok(Json(releases))
+}
+
+async fn install(
+    State(client): State<Arc<Client>>,
+    Path(namespace): Path<String>,
+    Json(params): Json<InstallParams>,
+) -> Result<Json<ReleaseReport>> {
+    let report = release_service::install(client, &namespace, params).await?;
+    Ok(Json(report))
+}
+
+async fn get_release(
+    State(client): State<Arc<Client>>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> Result<Json<Release>> {
+    let release = release_service::get(client, &namespace, &name).await?;
+    Ok(Json(release))
+}
+
+async fn upgrade(
+    State(client): State<Arc<Client>>,
+    Path((namespace, name)): Path<(String, String)>,
+    params: Option<Json<UpgradeParams>>,
+) -> Result<Json<ReleaseReport>> {
+    let params = params.map(|Json(params)| params).unwrap_or_default();
+    let report = release_service::upgrade(client, &namespace, &name, params).await?;
+    Ok(Json(report))
+}
+
+async fn uninstall(
+    State(client): State<Arc<Client>>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> Result<Json<Vec<DeleteResult>>> {
+    let results = release_service::uninstall(client, &namespace, &name).await?;
+    Ok(Json(results))
+}
+
+async fn history(
+    State(client): State<Arc<Client>>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> Result<Json<Vec<ReleaseSummary>>> {
+    let revisions = release_service::history(client, &namespace, &name).await?;
+    Ok(Json(revisions))
+}
+
+async fn rollback(
+    State(client): State<Arc<Client>>,
+    Path((namespace, name)): Path<(String, String)>,
+    params: Option<Json<ReleaseRollbackParams>>,
+) -> Result<Json<ReleaseReport>> {
+    let params = params.map(|Json(params)| params).unwrap_or_default();
+    let report = release_service::rollback(client, &namespace, &name, params).await?;
+    Ok(Json(report))
+}
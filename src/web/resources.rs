@@ -0,0 +1,90 @@
+// Generic CRUD over any resource served by the cluster, addressed like the
+// Kubernetes API itself. The core group is written as `core`, e.g.
+// `/core/v1/namespaces/default/persistentvolumeclaims`.
+use crate::models::deployment::PatchQuery;
+use crate::models::resource::{ResourcePath, ResourceQuery};
+use crate::{services::resource_service, Result};
+use axum::http::{header, HeaderMap};
+use axum::{
+    body::Bytes,
+    extract::{Json, Path, Query, State},
+    routing::get,
+    Router,
+};
+use kube::api::DynamicObject;
+use kube::Client;
+use std::sync::Arc;
+
+pub fn routes(client: Arc<Client>) -> Router {
+    Router::new()
+        .route(
+            "/:group/:version/namespaces/:namespace/:plural",
+            get(list).post(create),
+        )
+        .route(
+            "/:group/:version/namespaces/:namespace/:plural/:name",
+            get(get_resource).put(replace).patch(patch).delete(delete),
+        )
+        .route("/:group/:version/:plural", get(list).post(create))
+        .route(
+            "/:group/:version/:plural/:name",
+            get(get_resource).put(replace).patch(patch).delete(delete),
+        )
+        .with_state(client)
+}
+
+async fn list(
+    State(client): State<Arc<Client>>,
+    Path(path): Path<ResourcePath>,
+    Query(query): Query<ResourceQuery>,
+) -> Result<Json<Vec<DynamicObject>>> {
+    let resources = resource_service::list(client, &path, &query).await?;
+    Ok(Json(resources))
+}
+
+async fn get_resource(
+    State(client): State<Arc<Client>>,
+    Path(path): Path<ResourcePath>,
+) -> Result<Json<DynamicObject>> {
+    let resource = resource_service::get(client, &path).await?;
+    Ok(Json(resource))
+}
+
+async fn create(
+    State(client): State<Arc<Client>>,
+    Path(path): Path<ResourcePath>,
+    Json(object): Json<DynamicObject>,
+) -> Result<Json<DynamicObject>> {
+    let created = resource_service::create(client, &path, object).await?;
+    Ok(Json(created))
+}
+
+async fn replace(
+    State(client): State<Arc<Client>>,
+    Path(path): Path<ResourcePath>,
+    Json(object): Json<DynamicObject>,
+) -> Result<Json<DynamicObject>> {
+    let replaced = resource_service::replace(client, &path, object).await?;
+    Ok(Json(replaced))
+}
+
+async fn patch(
+    State(client): State<Arc<Client>>,
+    Path(path): Path<ResourcePath>,
+    Query(query): Query<PatchQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<DynamicObject>> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    let patched = resource_service::patch(client, &path, query, content_type, &body).await?;
+    Ok(Json(patched))
+}
+
+async fn delete(
+    State(client): State<Arc<Client>>,
+    Path(path): Path<ResourcePath>,
+) -> Result<String> {
+    resource_service::delete(client, &path).await
+}